@@ -0,0 +1,256 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Lenient reads with type coercion
+//!
+//! Values from hand-written files or environment variable overrides are often stringly typed.
+//! `Coercing` reads values like `TomlValueReadTypeExt` does, but converts them to the requested
+//! type where the `CoercionPolicy` allows it.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::coerce::Coercing;
+//!
+//! let toml : toml::Value = toml::from_str("port = \"8080\"\ndebug = \"yes\"").unwrap();
+//! let coercing = Coercing::new(&toml);
+//!
+//! assert_eq!(coercing.read_int("port").unwrap(), Some(8080));
+//! assert_eq!(coercing.read_bool("debug").unwrap(), Some(true));
+//! ```
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::read::TomlValueReadExt;
+
+/// Describes which conversions `Coercing` does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoercionPolicy {
+    /// Parse strings when reading integers, floats and booleans
+    pub parse_strings: bool,
+
+    /// Accept integers when reading floats
+    pub int_to_float: bool,
+
+    /// Accept the integers `0` and `1` when reading booleans
+    pub int_to_bool: bool,
+
+    /// Accept integers, floats and booleans when reading strings
+    pub to_string: bool,
+
+    /// The strings which are parsed to `true`, compared case-insensitively
+    pub true_strings: Vec<String>,
+
+    /// The strings which are parsed to `false`, compared case-insensitively
+    pub false_strings: Vec<String>,
+}
+
+impl CoercionPolicy {
+    /// A policy which does not coerce anything
+    pub fn strict() -> Self {
+        CoercionPolicy {
+            parse_strings: false,
+            int_to_float: false,
+            int_to_bool: false,
+            to_string: false,
+            true_strings: vec![],
+            false_strings: vec![],
+        }
+    }
+}
+
+impl Default for CoercionPolicy {
+    /// A policy which does all conversions, parsing `true`, `yes`, `on` and `1` as `true` and
+    /// `false`, `no`, `off` and `0` as `false`
+    fn default() -> Self {
+        let strings = |s: &[&str]| s.iter().map(|s| String::from(*s)).collect();
+
+        CoercionPolicy {
+            parse_strings: true,
+            int_to_float: true,
+            int_to_bool: true,
+            to_string: true,
+            true_strings: strings(&["true", "yes", "on", "1"]),
+            false_strings: strings(&["false", "no", "off", "0"]),
+        }
+    }
+}
+
+/// Reads from a document, coercing values to the requested types
+///
+/// If a value cannot be coerced, `Error::TypeError` is returned, as with the strict getters of
+/// `TomlValueReadTypeExt`.
+#[derive(Debug, Clone)]
+pub struct Coercing<'doc> {
+    document: &'doc Value,
+    policy: CoercionPolicy,
+}
+
+impl<'doc> Coercing<'doc> {
+    /// Read from `document` using the default `CoercionPolicy`
+    pub fn new(document: &'doc Value) -> Self {
+        Coercing::with_policy(document, CoercionPolicy::default())
+    }
+
+    pub fn with_policy(document: &'doc Value, policy: CoercionPolicy) -> Self {
+        Coercing { document, policy }
+    }
+
+    pub fn policy(&self) -> &CoercionPolicy {
+        &self.policy
+    }
+
+    pub fn read_int(&self, query: &str) -> Result<Option<i64>> {
+        self.read_coerced(query, "Integer", |policy, val| match val {
+            Value::Integer(i) => Some(*i),
+            Value::String(s) if policy.parse_strings => s.trim().parse().ok(),
+            _ => None,
+        })
+    }
+
+    pub fn read_float(&self, query: &str) -> Result<Option<f64>> {
+        self.read_coerced(query, "Float", |policy, val| match val {
+            Value::Float(f) => Some(*f),
+            Value::Integer(i) if policy.int_to_float => Some(*i as f64),
+            Value::String(s) if policy.parse_strings => s.trim().parse().ok(),
+            _ => None,
+        })
+    }
+
+    pub fn read_bool(&self, query: &str) -> Result<Option<bool>> {
+        self.read_coerced(query, "Boolean", |policy, val| match val {
+            Value::Boolean(b) => Some(*b),
+            Value::Integer(0) if policy.int_to_bool => Some(false),
+            Value::Integer(1) if policy.int_to_bool => Some(true),
+            Value::String(s) if policy.parse_strings => {
+                let s = s.trim();
+                if policy
+                    .true_strings
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(s))
+                {
+                    Some(true)
+                } else if policy
+                    .false_strings
+                    .iter()
+                    .any(|f| f.eq_ignore_ascii_case(s))
+                {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+    }
+
+    pub fn read_string(&self, query: &str) -> Result<Option<String>> {
+        self.read_coerced(query, "String", |policy, val| match val {
+            Value::String(s) => Some(s.clone()),
+            Value::Integer(i) if policy.to_string => Some(i.to_string()),
+            Value::Float(f) if policy.to_string => Some(f.to_string()),
+            Value::Boolean(b) if policy.to_string => Some(b.to_string()),
+            _ => None,
+        })
+    }
+
+    fn read_coerced<T, F>(
+        &self,
+        query: &str,
+        typename: &'static str,
+        coerce: F,
+    ) -> Result<Option<T>>
+    where
+        F: FnOnce(&CoercionPolicy, &Value) -> Option<T>,
+    {
+        match self.document.read(query)? {
+            None => Ok(None),
+            Some(val) => coerce(&self.policy, val)
+                .map(Some)
+                .ok_or_else(|| Error::TypeError(typename, crate::util::name_of_val(val))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_coerce_default_policy() {
+        let toml: Value = toml_from_str(
+            r#"
+        port = " 8080 "
+        ratio = 2
+        half = "0.5"
+        debug = "Yes"
+        verbose = 0
+        count = 3
+        "#,
+        )
+        .unwrap();
+        let coercing = Coercing::new(&toml);
+
+        assert_eq!(coercing.read_int("port").unwrap(), Some(8080));
+        assert_eq!(coercing.read_float("ratio").unwrap(), Some(2.0));
+        assert_eq!(coercing.read_float("half").unwrap(), Some(0.5));
+        assert_eq!(coercing.read_bool("debug").unwrap(), Some(true));
+        assert_eq!(coercing.read_bool("verbose").unwrap(), Some(false));
+        assert_eq!(
+            coercing.read_string("count").unwrap(),
+            Some(String::from("3"))
+        );
+        assert!(coercing.read_int("nonexistent").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_coerce_not_coercible() {
+        let toml: Value = toml_from_str("name = \"foo\"\ncount = 3").unwrap();
+        let coercing = Coercing::new(&toml);
+
+        let err = coercing.read_int("name").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Integer", "String")));
+
+        let err = coercing.read_bool("count").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Boolean", "Integer")));
+    }
+
+    #[test]
+    fn test_coerce_strict_policy() {
+        let toml: Value = toml_from_str(
+            r#"
+        port = " 8080 "
+        ratio = 2
+        verbose = 0
+        count = 3
+        "#,
+        )
+        .unwrap();
+        let coercing = Coercing::with_policy(&toml, CoercionPolicy::strict());
+
+        assert!(coercing.read_int("port").is_err());
+        assert!(coercing.read_float("ratio").is_err());
+        assert!(coercing.read_bool("verbose").is_err());
+        assert!(coercing.read_string("count").is_err());
+        assert_eq!(coercing.read_int("count").unwrap(), Some(3));
+    }
+
+    #[test]
+    fn test_coerce_custom_bool_strings() {
+        let toml: Value = toml_from_str("name = \"foo\"\ndebug = \"Yes\"").unwrap();
+        let policy = CoercionPolicy {
+            true_strings: vec![String::from("foo")],
+            ..CoercionPolicy::default()
+        };
+        let coercing = Coercing::with_policy(&toml, policy);
+
+        assert_eq!(coercing.read_bool("name").unwrap(), Some(true));
+        assert!(coercing.read_bool("debug").is_err());
+    }
+}
@@ -0,0 +1,425 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Reading many values and collecting all errors
+//!
+//! The getters of `TomlValueReadTypeExt` fail on the first error. When checking a whole
+//! configuration file, it is more helpful to report every problem at once. `Collecting` reads
+//! like the typed getters do, but records errors instead of returning them, so that all of them
+//! can be reported by `Collecting::finish()`.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::collect::Collecting;
+//!
+//! let toml : toml::Value = toml::from_str("port = \"80\"\nname = 1").unwrap();
+//! let mut collecting = Collecting::new(&toml);
+//!
+//! let port = collecting.read_int("port");
+//! let name = collecting.read_str("name");
+//! let host = collecting.require_str("host");
+//! assert!(port.is_none() && name.is_none() && host.is_none());
+//!
+//! let errors = collecting.finish().unwrap_err();
+//! assert_eq!(errors.len(), 3);
+//! ```
+
+use std::fmt;
+
+use toml::map::Map;
+use toml::value::Datetime;
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::read::{BoundedInteger, TomlValueReadExt, TomlValueReadTypeExt};
+use crate::value::ValueType;
+
+/// The errors collected by `Collecting`, each with the query which caused it
+#[derive(Debug, Default)]
+pub struct Errors(Vec<(String, Error)>);
+
+impl Errors {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Error)> {
+        self.0.iter()
+    }
+
+    pub fn into_inner(self) -> Vec<(String, Error)> {
+        self.0
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = (String, Error);
+    type IntoIter = ::std::vec::IntoIter<(String, Error)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for Errors {
+    /// One line per error, prefixed with its query unless the error names the query itself
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (query, error)) in self.0.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            if names_query(error) {
+                write!(f, "{}", error)?;
+            } else {
+                write!(f, "'{}': {}", query, error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the message of `error` contains the query it was recorded for
+fn names_query(error: &Error) -> bool {
+    match error {
+        Error::NotAvailable(..) | Error::OutOfRange(..) | Error::UnitParseError(..) => true,
+        #[cfg(feature = "typed")]
+        Error::Deserialize(..) => true,
+        _ => false,
+    }
+}
+
+impl ::std::error::Error for Errors {}
+
+macro_rules! make_collecting_getter {
+    ($fnname:ident, $require:ident, $rettype:ty) => {
+        #[doc = concat!("Like `TomlValueReadTypeExt::", stringify!($fnname), "`, but records errors")]
+        pub fn $fnname(&mut self, query: &str) -> Option<$rettype> {
+            let result = self.document.$fnname(query);
+            self.record(query, result)
+        }
+
+        #[doc = concat!("Like `TomlValueReadTypeExt::", stringify!($require), "`, but records errors")]
+        pub fn $require(&mut self, query: &str) -> Option<$rettype> {
+            let result = self.document.$require(query).map(Some);
+            self.record(query, result)
+        }
+    };
+}
+
+/// Reads from a document, collecting errors instead of failing on the first one
+///
+/// All getters return `None` if there is no value or if reading the value failed, in the latter
+/// case the error is recorded. The `require_*` getters record `Error::NotAvailable` if there is
+/// no value.
+#[derive(Debug)]
+pub struct Collecting<'doc> {
+    document: &'doc Value,
+    errors: Vec<(String, Error)>,
+}
+
+impl<'doc> Collecting<'doc> {
+    pub fn new(document: &'doc Value) -> Self {
+        Collecting {
+            document,
+            errors: vec![],
+        }
+    }
+
+    /// The errors recorded so far
+    pub fn errors(&self) -> &[(String, Error)] {
+        &self.errors
+    }
+
+    /// Returns whether no errors were recorded so far
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Finish reading, returning all recorded errors if there are any
+    pub fn finish(self) -> ::std::result::Result<(), Errors> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Errors(self.errors))
+        }
+    }
+
+    make_collecting_getter!(read_string, require_string, String);
+    make_collecting_getter!(read_int, require_int, i64);
+    make_collecting_getter!(read_float, require_float, f64);
+    make_collecting_getter!(read_bool, require_bool, bool);
+    make_collecting_getter!(read_datetime, require_datetime, Datetime);
+    make_collecting_getter!(read_array, require_array, Vec<Value>);
+    make_collecting_getter!(read_table, require_table, Map<String, Value>);
+    make_collecting_getter!(read_str, require_str, &'doc str);
+    make_collecting_getter!(read_array_ref, require_array_ref, &'doc [Value]);
+    make_collecting_getter!(read_table_ref, require_table_ref, &'doc Map<String, Value>);
+
+    /// Like `TomlValueReadTypeExt::read_int_as`, but records errors
+    pub fn read_int_as<N: BoundedInteger>(&mut self, query: &str) -> Option<N> {
+        let result = self.document.read_int_as(query);
+        self.record(query, result)
+    }
+
+    /// Read the value at `query`, recording `Error::TypeError` if it is not of type `expected`
+    ///
+    /// Returns `None` if there is no value or if it has the wrong type.
+    pub fn expect(&mut self, query: &str, expected: ValueType) -> Option<&'doc Value> {
+        let result = self
+            .document
+            .read(query)
+            .and_then(|v| v.map(|v| check_type(v, expected)).transpose());
+        self.record(query, result)
+    }
+
+    /// Like `Collecting::expect`, but records `Error::NotAvailable` if there is no value
+    pub fn require(&mut self, query: &str, expected: ValueType) -> Option<&'doc Value> {
+        let result = self.document.read(query).and_then(|v| match v {
+            None => Err(Error::NotAvailable(String::from(query))),
+            Some(v) => check_type(v, expected).map(Some),
+        });
+        self.record(query, result)
+    }
+
+    /// Like `TomlValueReadExt::read_deserialized`, but records errors
+    #[cfg(feature = "typed")]
+    pub fn read_deserialized<D>(&mut self, query: &str) -> Option<D>
+    where
+        D: serde::Deserialize<'doc>,
+    {
+        let result = self.document.read_deserialized(query);
+        self.record(query, result)
+    }
+
+    /// Like `TomlValueReadExt::read_partial`, but records errors
+    ///
+    /// Each field is checked on its own, so an error is recorded for every field which fails to
+    /// deserialize, for the location of the field. Other errors are recorded for `P::LOCATION`.
+    #[cfg(feature = "typed")]
+    pub fn read_partial<P>(&mut self) -> Option<P::Output>
+    where
+        P: crate::read::Partial<'doc>,
+    {
+        let e = match self.document.read_partial::<P>() {
+            Ok(output) => return output,
+            Err(e) => e,
+        };
+
+        let errors = match crate::read::partial_errors::<P>(self.document) {
+            Some(errors) if !errors.is_empty() => errors,
+            _ => vec![e],
+        };
+        for e in errors {
+            let query = match e {
+                Error::Deserialize(ref path, _) => path.clone(),
+                _ => String::from(P::LOCATION),
+            };
+            self.errors.push((query, e));
+        }
+        None
+    }
+
+    fn record<T>(&mut self, query: &str, result: Result<Option<T>>) -> Option<T> {
+        match result {
+            Ok(value) => value,
+            Err(e) => {
+                self.errors.push((String::from(query), e));
+                None
+            }
+        }
+    }
+}
+
+fn check_type(value: &Value, expected: ValueType) -> Result<&Value> {
+    if ValueType::of(value) == expected {
+        Ok(value)
+    } else {
+        Err(Error::TypeError(
+            expected.name(),
+            crate::util::name_of_val(value),
+        ))
+    }
+}
+
+/// Check that all `expected` values exist in `document` and have the expected types
+///
+/// Returns all violations at once.
+pub fn check_types(
+    document: &Value,
+    expected: &[(&str, ValueType)],
+) -> ::std::result::Result<(), Errors> {
+    let mut collecting = Collecting::new(document);
+    for (query, ty) in expected {
+        let _ = collecting.require(query, *ty);
+    }
+    collecting.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_collect_all_errors() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "foo"
+        port = "80"
+        huge = 70000
+        ratio = 0.5
+
+        [server]
+        debug = true
+        "#,
+        )
+        .unwrap();
+        let mut collecting = Collecting::new(&toml);
+
+        assert_eq!(collecting.read_str("name"), Some("foo"));
+        assert_eq!(collecting.read_float("ratio"), Some(0.5));
+        assert_eq!(collecting.read_bool("server.debug"), Some(true));
+        assert!(collecting.read_int("nonexistent").is_none());
+        assert!(collecting.is_ok());
+
+        assert!(collecting.read_int("port").is_none());
+        assert!(collecting.read_int_as::<u16>("huge").is_none());
+        assert!(collecting.require_string("host").is_none());
+        assert!(collecting.read_int("name.foo").is_none());
+        assert!(collecting.require("name.[0]", ValueType::Integer).is_none());
+        assert!(!collecting.is_ok());
+
+        let errors = collecting.finish().unwrap_err();
+        let errors = errors.into_inner();
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors[0].0, "port");
+        assert!(is_match!(
+            errors[0].1,
+            Error::TypeError("Integer", "String")
+        ));
+        assert!(is_match!(errors[1].1, Error::OutOfRange(..)));
+        assert!(is_match!(errors[2].1, Error::NotAvailable(..)));
+        assert_eq!(errors[3].0, "name.foo");
+        assert_eq!(errors[4].0, "name.[0]");
+    }
+
+    #[test]
+    fn test_collect_finish_ok() {
+        let toml: Value = toml_from_str("name = \"foo\"\n[server]").unwrap();
+        let mut collecting = Collecting::new(&toml);
+        assert_eq!(collecting.require_str("name"), Some("foo"));
+        assert!(collecting.require("server", ValueType::Table).is_some());
+        assert!(collecting.finish().is_ok());
+    }
+
+    #[test]
+    fn test_check_types() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "foo"
+        port = "80"
+
+        [server]
+        debug = true
+        "#,
+        )
+        .unwrap();
+
+        assert!(check_types(
+            &toml,
+            &[("name", ValueType::String), ("server", ValueType::Table)]
+        )
+        .is_ok());
+
+        let errors = check_types(
+            &toml,
+            &[
+                ("name", ValueType::String),
+                ("port", ValueType::Integer),
+                ("server.debug", ValueType::Boolean),
+                ("server.host", ValueType::String),
+            ],
+        )
+        .unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(
+            errors.to_string(),
+            "'port': Type Error. Requested Integer, but got String\n\
+             Value at 'server.host' not there"
+        );
+    }
+
+    #[cfg(feature = "typed")]
+    #[test]
+    fn test_collect_deserialized() {
+        let toml: Value = toml_from_str("name = \"foo\"\nhuge = 70000").unwrap();
+        let mut collecting = Collecting::new(&toml);
+
+        assert_eq!(collecting.read_deserialized::<&str>("name"), Some("foo"));
+        assert!(collecting.read_deserialized::<u16>("huge").is_none());
+        assert_eq!(collecting.errors().len(), 1);
+    }
+
+    #[cfg(feature = "typed")]
+    #[test]
+    fn test_collect_partial_fields() {
+        use crate::Partial;
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Deserialize, Serialize, Partial)]
+        #[location = "server"]
+        struct Server {
+            port: u16,
+            debug: bool,
+            workers: Option<u8>,
+
+            #[location = "client.name"]
+            name: String,
+        }
+
+        let toml: Value = toml_from_str(
+            r#"
+        [server]
+        port = "80"
+        debug = true
+        workers = 1000
+
+        [client]
+        name = 1
+        "#,
+        )
+        .unwrap();
+
+        let mut collecting = Collecting::new(&toml);
+        assert!(collecting.read_partial::<Server>().is_none());
+
+        let errors = collecting.finish().unwrap_err();
+        let queries = errors.iter().map(|(q, _)| q.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            queries,
+            vec!["server.port", "server.workers", "client.name"]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "at server.port: invalid type: string \"80\", expected u16\n\
+             at server.workers: invalid value: integer `1000`, expected u8\n\
+             at client.name: invalid type: integer `1`, expected a string"
+        );
+
+        let toml: Value = toml_from_str("[server]\nport = 80\n[client]\nname = \"a\"").unwrap();
+        let mut collecting = Collecting::new(&toml);
+        assert!(collecting.read_partial::<Server>().is_none());
+        let errors = collecting.finish().unwrap_err();
+        assert_eq!(errors.to_string(), "at server: missing field `debug`");
+    }
+}
@@ -0,0 +1,539 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! A `Deserializer` which borrows from a `toml::Value`
+//!
+//! `toml::Value` only implements `serde::Deserializer` by value, so deserializing a part of a
+//! document that way means cloning the whole subtree first. `ValueDeserializer` deserializes
+//! from a `&toml::Value` in place instead, and lets types borrow strings from the document.
+//!
+//! ```rust
+//! extern crate serde;
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use serde::Deserialize;
+//! use toml_query::de::ValueDeserializer;
+//!
+//! let toml : toml::Value = toml::from_str("names = [\"a\", \"b\"]").unwrap();
+//! let names = toml.get("names").unwrap();
+//!
+//! let borrowed = <Vec<&str>>::deserialize(ValueDeserializer::new(names)).unwrap();
+//! assert_eq!(borrowed, vec!["a", "b"]);
+//! ```
+
+use std::fmt;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, Error as DeError, Unexpected};
+use toml::Value;
+use toml_datetime::de::DatetimeDeserializer;
+
+use crate::query::{Query, Segment};
+
+/// The error type of `ValueDeserializer`
+///
+/// Besides the message, the error knows the path of the value which failed to deserialize,
+/// relative to the value the deserializer was created for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    path: Vec<Segment>,
+    message: String,
+
+    // The field whose absence caused the error, if any
+    missing: Option<&'static str>,
+}
+
+impl Error {
+    /// The path of the value which failed to deserialize
+    pub fn path(&self) -> &[Segment] {
+        &self.path
+    }
+
+    /// The message of the error, without the path
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The name of the missing field, if the error is about one
+    pub(crate) fn missing(&self) -> Option<&'static str> {
+        self.missing
+    }
+
+    /// Prepend `prefix` to the path of this error
+    pub(crate) fn prefixed<I>(mut self, prefix: I) -> Self
+    where
+        I: IntoIterator<Item = Segment>,
+    {
+        let mut path = prefix.into_iter().collect::<Vec<_>>();
+        path.append(&mut self.path);
+        self.path = path;
+        self
+    }
+
+    /// The path of this error and its message, as used by `crate::error::Error::Deserialize`
+    pub(crate) fn into_parts(self) -> (String, String) {
+        (Query::from(self.path).to_string(), self.message)
+    }
+
+    /// Remove the first segment from the path of this error
+    pub(crate) fn without_first(mut self) -> Self {
+        if !self.path.is_empty() {
+            self.path.remove(0);
+        }
+        self
+    }
+
+    fn at(self, segment: Segment) -> Self {
+        self.prefixed(Some(segment))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "at {}: {}", Query::from(self.path.clone()), self.message)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error {
+            path: vec![],
+            message: msg.to_string(),
+            missing: None,
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error {
+            missing: Some(field),
+            ..Error::custom(format_args!("missing field `{}`", field))
+        }
+    }
+}
+
+/// Deserializes from a borrowed `toml::Value`
+///
+/// Behaves like deserializing with `toml::from_str()`: datetimes can be deserialized to
+/// `toml::value::Datetime`, `Option`s are always `Some` (a missing key is `None`), and enums are
+/// read from strings (unit variants) or from tables with exactly one key.
+#[derive(Debug, Clone, Copy)]
+pub struct ValueDeserializer<'de> {
+    value: &'de Value,
+}
+
+impl<'de> ValueDeserializer<'de> {
+    pub fn new(value: &'de Value) -> Self {
+        ValueDeserializer { value }
+    }
+}
+
+/// Deserialize a `T` from `value`, without cloning it
+pub fn from_value<'de, T: de::Deserialize<'de>>(value: &'de Value) -> Result<T, Error> {
+    T::deserialize(ValueDeserializer::new(value))
+}
+
+/// Deserialize a `T` from a table which is given as `(key, value)` pairs
+pub(crate) fn from_entries<'de, T>(entries: Vec<(&'de str, &'de Value)>) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(EntriesDeserializer(entries))
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            Value::String(s) => visitor.visit_borrowed_str(s),
+            Value::Datetime(d) => visitor.visit_map(DatetimeDeserializer::new(*d)),
+            Value::Array(a) => {
+                let mut access = SeqAccess {
+                    iter: a.iter().enumerate(),
+                };
+                let value = visitor.visit_seq(&mut access)?;
+                match access.iter.len() {
+                    0 => Ok(value),
+                    _ => Err(Error::invalid_length(a.len(), &"fewer elements in array")),
+                }
+            }
+            Value::Table(t) => {
+                visit_entries(t.iter().map(|(k, v)| (k.as_str(), v)), t.len(), visitor)
+            }
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.value {
+            Value::String(s) => visitor.visit_enum(BorrowedStrDeserializer::new(s)),
+            Value::Table(t) if t.len() == 1 => {
+                let (variant, value) = t.iter().next().unwrap(); // safe because len() == 1
+                visitor.visit_enum(VariantDeserializer { variant, value })
+            }
+            Value::Table(t) => Err(Error::invalid_length(t.len(), &"exactly 1 element")),
+            other => Err(Error::invalid_type(
+                unexpected(other),
+                &"string or table with exactly 1 element",
+            )),
+        }
+    }
+
+    // `None` is a missing key, so a present value is always `Some`
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+        bytes byte_buf map unit_struct tuple_struct struct
+        tuple ignored_any identifier
+    }
+}
+
+fn visit_entries<'de, I, V>(iter: I, len: usize, visitor: V) -> Result<V::Value, Error>
+where
+    I: Iterator<Item = (&'de str, &'de Value)>,
+    V: de::Visitor<'de>,
+{
+    let mut access = MapAccess { iter, value: None };
+    let value = visitor.visit_map(&mut access)?;
+    match access.iter.next() {
+        None => Ok(value),
+        Some(_) => Err(Error::invalid_length(len, &"fewer elements in table")),
+    }
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Boolean(b) => Unexpected::Bool(*b),
+        Value::Integer(i) => Unexpected::Signed(*i),
+        Value::Float(f) => Unexpected::Float(*f),
+        Value::String(s) => Unexpected::Str(s),
+        Value::Datetime(_) => Unexpected::Other("datetime"),
+        Value::Array(_) => Unexpected::Seq,
+        Value::Table(_) => Unexpected::Map,
+    }
+}
+
+/// A table which is given as `(key, value)` pairs
+struct EntriesDeserializer<'de>(Vec<(&'de str, &'de Value)>);
+
+impl<'de> de::Deserializer<'de> for EntriesDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = self.0.len();
+        visit_entries(self.0.into_iter(), len, visitor)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+        bytes byte_buf map unit_struct tuple_struct struct option newtype_struct
+        tuple ignored_any identifier enum
+    }
+}
+
+struct SeqAccess<'de> {
+    iter: std::iter::Enumerate<std::slice::Iter<'de, Value>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            None => Ok(None),
+            Some((i, value)) => seed
+                .deserialize(ValueDeserializer::new(value))
+                .map(Some)
+                .map_err(|e| e.at(Segment::Index(i))),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct MapAccess<'de, I> {
+    iter: I,
+    value: Option<(&'de str, &'de Value)>,
+}
+
+impl<'de, I> de::MapAccess<'de> for MapAccess<'de, I>
+where
+    I: Iterator<Item = (&'de str, &'de Value)>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.value = Some((key, value));
+                seed.deserialize(BorrowedStrDeserializer::<Error>::new(key))
+                    .map(Some)
+                    .map_err(|e| e.at(Segment::Key(String::from(key))))
+            }
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            None => Err(Error::custom("value is missing")),
+            Some((key, value)) => seed
+                .deserialize(ValueDeserializer::new(value))
+                .map_err(|e| e.at(Segment::Key(String::from(key)))),
+        }
+    }
+}
+
+/// An enum variant, given as a table with exactly one key
+struct VariantDeserializer<'de> {
+    variant: &'de str,
+    value: &'de Value,
+}
+
+impl<'de> VariantDeserializer<'de> {
+    fn annotate(&self, e: Error) -> Error {
+        e.at(Segment::Key(String::from(self.variant)))
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for VariantDeserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(BorrowedStrDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantDeserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        match self.value {
+            Value::Array(a) if a.is_empty() => Ok(()),
+            Value::Table(t) if t.is_empty() => Ok(()),
+            other => Err(self.annotate(Error::invalid_type(unexpected(other), &"empty table"))),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(ValueDeserializer::new(self.value))
+            .map_err(|e| self.annotate(e))
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(ValueDeserializer::new(self.value), visitor)
+            .map_err(|e| self.annotate(e))
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(ValueDeserializer::new(self.value), visitor)
+            .map_err(|e| self.annotate(e))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::BTreeMap;
+    use toml::from_str as toml_from_str;
+    use toml::value::Datetime;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Kind {
+        Unit,
+        Newtype(i64),
+        Tuple(i64, i64),
+        Struct { a: bool },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Borrowing<'a> {
+        name: &'a str,
+        tags: Vec<&'a str>,
+        port: u16,
+        ratio: f64,
+        missing: Option<i64>,
+        present: Option<bool>,
+        date: Datetime,
+        kinds: Vec<Kind>,
+        nested: BTreeMap<&'a str, i64>,
+    }
+
+    #[test]
+    fn test_deserialize_borrowing() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "foo"
+        tags = ["a", "b"]
+        port = 8080
+        ratio = 0.5
+        present = true
+        date = 1979-05-27T07:32:00Z
+        kinds = ["Unit", { Newtype = 1 }, { Tuple = [1, 2] }, { Struct = { a = true } }]
+
+        [nested]
+        x = 1
+        "#,
+        )
+        .unwrap();
+
+        let b: Borrowing<'_> = from_value(&toml).unwrap();
+        assert_eq!(b.name, "foo");
+        assert_eq!(b.tags, vec!["a", "b"]);
+        assert_eq!(b.port, 8080);
+        assert_eq!(b.ratio, 0.5);
+        assert_eq!(b.missing, None);
+        assert_eq!(b.present, Some(true));
+        assert_eq!(b.date.to_string(), "1979-05-27T07:32:00Z");
+        assert_eq!(
+            b.kinds,
+            vec![
+                Kind::Unit,
+                Kind::Newtype(1),
+                Kind::Tuple(1, 2),
+                Kind::Struct { a: true }
+            ]
+        );
+        assert_eq!(b.nested.get("x"), Some(&1));
+
+        // the string is borrowed from the document, not copied
+        let name = toml.get("name").and_then(Value::as_str).unwrap();
+        assert_eq!(b.name.as_ptr(), name.as_ptr());
+    }
+
+    #[test]
+    fn test_deserialize_errors() {
+        let toml: Value = toml_from_str("port = 70000\nname = 1\nkind = { A = 1, B = 2 }").unwrap();
+
+        assert!(from_value::<u16>(toml.get("port").unwrap()).is_err());
+        assert!(from_value::<&str>(toml.get("name").unwrap()).is_err());
+        assert!(from_value::<Kind>(toml.get("kind").unwrap()).is_err());
+        assert!(from_value::<(i64, i64)>(&Value::Array(vec![Value::Integer(1)])).is_err());
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Server {
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Servers {
+        web: Vec<Server>,
+        kind: Option<Kind>,
+    }
+
+    #[test]
+    fn test_error_path() {
+        let toml: Value = toml_from_str("[[web]]\nport = 1\n[[web]]\nport = \"80\"\n").unwrap();
+        let err = from_value::<Servers>(&toml).unwrap_err();
+        assert_eq!(
+            err.path(),
+            &[
+                Segment::Key(String::from("web")),
+                Segment::Index(1),
+                Segment::Key(String::from("port"))
+            ]
+        );
+        assert_eq!(
+            err.to_string(),
+            "at web.[1].port: invalid type: string \"80\", expected u16"
+        );
+
+        let toml: Value = toml_from_str("web = []\nkind = { Newtype = \"a\" }").unwrap();
+        let err = from_value::<Servers>(&toml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at kind.Newtype: invalid type: string \"a\", expected i64"
+        );
+
+        let err = from_value::<Servers>(&Value::Integer(1)).unwrap_err();
+        assert!(err.path().is_empty());
+        assert_eq!(err.to_string(), err.message());
+    }
+
+    #[test]
+    fn test_from_entries() {
+        let a = Value::Integer(1);
+        let b = Value::String(String::from("b"));
+
+        let map: BTreeMap<&str, &str> = from_entries(vec![("b", &b)]).unwrap();
+        assert_eq!(map.get("b"), Some(&"b"));
+
+        let res: Result<BTreeMap<&str, &str>, _> = from_entries(vec![("a", &a)]);
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Expanding environment variables in string values
+//!
+//! `expand_env()` replaces references to environment variables in all string values of a
+//! document:
+//!
+//! * `$NAME` and `${ENV:NAME}` are replaced by the value of the variable `NAME`
+//! * `${NAME:-default}` and `${ENV:NAME:-default}` are replaced by `default` if `NAME` is not set
+//! * `$$` is a literal `$`, unless it is followed by `{`
+//!
+//! A plain `${name}` is left untouched, so it can be resolved by `interpolate::interpolate()`
+//! afterwards. So is the escaped `$${`, which only `interpolate()` turns into a literal `${`.
+//! Without interpolation, `$${HOME}` therefore stays `$${HOME}`, while `$$HOME` becomes `$HOME`.
+//! A literal `${` cannot be written if the document is not interpolated afterwards.
+//!
+//! The variables are looked up in a `VariableSource`, which is the process environment for
+//! `expand_env()`. `expand_env_with()` takes any other source, for example a `HashMap`:
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use std::collections::HashMap;
+//! use toml_query::env::expand_env_with;
+//! use toml_query::read::TomlValueReadTypeExt;
+//!
+//! let mut toml : toml::Value = toml::from_str(r#"
+//! data = "$HOME/data"
+//! host = "${ENV:HOST:-localhost}"
+//! "#).unwrap();
+//!
+//! let mut vars = HashMap::new();
+//! vars.insert(String::from("HOME"), String::from("/home/user"));
+//!
+//! expand_env_with(&mut toml, &vars).unwrap();
+//! assert_eq!(toml.read_string("data").unwrap().unwrap(), "/home/user/data");
+//! assert_eq!(toml.read_string("host").unwrap().unwrap(), "localhost");
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::BuildHasher;
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::query::{Query, Segment};
+
+/// A source of variables for `expand_env_with()`
+pub trait VariableSource {
+    /// Get the value of the variable `name`, `None` if it is not set
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// The environment of the process
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Environment;
+
+impl VariableSource for Environment {
+    fn get(&self, name: &str) -> Option<String> {
+        ::std::env::var(name).ok()
+    }
+}
+
+impl<S: BuildHasher> VariableSource for HashMap<String, String, S> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+impl VariableSource for BTreeMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        BTreeMap::get(self, name).cloned()
+    }
+}
+
+impl<F> VariableSource for F
+where
+    F: Fn(&str) -> Option<String>,
+{
+    fn get(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+/// Expand the environment variables in all string values of `document`
+///
+/// See documentation of `expand_env_with`
+pub fn expand_env(document: &mut Value) -> Result<()> {
+    expand_env_with(document, &Environment)
+}
+
+/// Expand the variables from `source` in all string values of `document`
+///
+/// Keys are not expanded. `$${` is kept for `interpolate::interpolate()`, see the module
+/// documentation.
+///
+/// Returns `Error::UndefinedVariable` if a variable without a default is not set and
+/// `Error::InterpolationError` for a malformed `${ENV:...}`. The document is not modified if an
+/// error is returned.
+pub fn expand_env_with<S>(document: &mut Value, source: &S) -> Result<()>
+where
+    S: VariableSource + ?Sized,
+{
+    let mut expanded = document.clone();
+    expand_value(&mut expanded, &Query::default(), source)?;
+    *document = expanded;
+    Ok(())
+}
+
+fn expand_value<S>(value: &mut Value, path: &Query, source: &S) -> Result<()>
+where
+    S: VariableSource + ?Sized,
+{
+    match value {
+        Value::String(s) if s.contains('$') => *s = expand_str(s, path, source)?,
+        Value::Table(tab) => {
+            for (key, value) in tab.iter_mut() {
+                let mut path = path.clone();
+                path.push(Segment::Key(key.clone()));
+                expand_value(value, &path, source)?;
+            }
+        }
+        Value::Array(arr) => {
+            for (idx, value) in arr.iter_mut().enumerate() {
+                let mut path = path.clone();
+                path.push(Segment::Index(idx));
+                expand_value(value, &path, source)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn expand_str<S>(s: &str, path: &Query, source: &S) -> Result<String>
+where
+    S: VariableSource + ?Sized,
+{
+    let mut expanded = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(r) = rest.strip_prefix("${") {
+            // an escaped reference, which is unescaped by `interpolate()`
+            expanded.push_str("$${");
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = r;
+        } else if let Some(r) = rest.strip_prefix('{') {
+            let end = match r.find('}') {
+                Some(end) => end,
+                None if r.starts_with("ENV:") => {
+                    let msg = format!("unterminated variable in '{}'", s);
+                    return Err(Error::InterpolationError(path.to_string(), msg));
+                }
+                None => {
+                    expanded.push('$');
+                    continue;
+                }
+            };
+
+            let inner = &r[..end];
+            let explicit = inner.starts_with("ENV:");
+            let (name, default) = match inner.trim_start_matches("ENV:").find(":-") {
+                Some(idx) => {
+                    let inner = inner.trim_start_matches("ENV:");
+                    (&inner[..idx], Some(&inner[idx + 2..]))
+                }
+                None => (inner.trim_start_matches("ENV:"), None),
+            };
+
+            if !explicit && default.is_none() {
+                // not a variable, but possibly a reference for `interpolate()`
+                expanded.push('$');
+                continue;
+            }
+
+            if name.is_empty() || !name.chars().all(is_name_char) {
+                let msg = format!("invalid variable name '{}'", name);
+                return Err(Error::InterpolationError(path.to_string(), msg));
+            }
+
+            match (source.get(name), default) {
+                (Some(value), _) => expanded.push_str(&value),
+                (None, Some(default)) => expanded.push_str(default),
+                (None, None) => {
+                    return Err(Error::UndefinedVariable(
+                        path.to_string(),
+                        String::from(name),
+                    ))
+                }
+            }
+            rest = &r[end + 1..];
+        } else {
+            let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+            if len == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+                expanded.push('$');
+                continue;
+            }
+
+            let name = &rest[..len];
+            match source.get(name) {
+                Some(value) => expanded.push_str(&value),
+                None => {
+                    return Err(Error::UndefinedVariable(
+                        path.to_string(),
+                        String::from(name),
+                    ))
+                }
+            }
+            rest = &rest[len..];
+        }
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    fn vars() -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+        vars.insert(String::from("HOME"), String::from("/home/user"));
+        vars.insert(String::from("HOST"), String::from("example.com"));
+        vars
+    }
+
+    fn expand(s: &str) -> Result<String> {
+        expand_str(s, &Query::default(), &vars())
+    }
+
+    #[test]
+    fn test_expand_str() {
+        assert_eq!(expand("$HOME/data").unwrap(), "/home/user/data");
+        assert_eq!(expand("${ENV:HOME}data").unwrap(), "/home/userdata");
+        assert_eq!(expand("${ENV:PORT:-80}").unwrap(), "80");
+        assert_eq!(expand("${PORT:-80}").unwrap(), "80");
+        assert_eq!(expand("${HOST:-localhost}").unwrap(), "example.com");
+        assert_eq!(expand("$$HOME costs $5 $").unwrap(), "$HOME costs $5 $");
+        assert_eq!(expand("${other.key}").unwrap(), "${other.key}");
+        assert_eq!(expand("${unterminated").unwrap(), "${unterminated");
+        assert_eq!(expand("$${HOST:-a} $$$${x}").unwrap(), "$${HOST:-a} $$${x}");
+    }
+
+    #[test]
+    fn test_expand_str_errors() {
+        assert!(is_match!(
+            expand("$PORT"),
+            Err(Error::UndefinedVariable(..))
+        ));
+        assert!(is_match!(
+            expand("${ENV:PORT}"),
+            Err(Error::UndefinedVariable(..))
+        ));
+        assert!(is_match!(
+            expand("${ENV:HOME"),
+            Err(Error::InterpolationError(..))
+        ));
+        assert!(is_match!(
+            expand("${ENV:a.b}"),
+            Err(Error::InterpolationError(..))
+        ));
+    }
+
+    #[test]
+    fn test_expand_document() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        port = 80
+        dirs = ["$HOME/a", "b"]
+
+        [server]
+        host = "${ENV:HOST}"
+        "#,
+        )
+        .unwrap();
+
+        expand_env_with(&mut toml, &vars()).unwrap();
+        assert_eq!(
+            toml.read("dirs.[0]").unwrap(),
+            Some(&Value::String(String::from("/home/user/a")))
+        );
+        assert_eq!(
+            toml.read("server.host").unwrap(),
+            Some(&Value::String(String::from("example.com")))
+        );
+        assert_eq!(toml.read("port").unwrap(), Some(&Value::Integer(80)));
+    }
+
+    #[test]
+    fn test_expand_then_interpolate() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        h = "x"
+        literal = "$${h}"
+        dir = "$HOME/${h}"
+        "#,
+        )
+        .unwrap();
+
+        expand_env_with(&mut toml, &vars()).unwrap();
+        crate::interpolate::interpolate(&mut toml).unwrap();
+        assert_eq!(
+            toml.read("literal").unwrap(),
+            Some(&Value::String(String::from("${h}")))
+        );
+        assert_eq!(
+            toml.read("dir").unwrap(),
+            Some(&Value::String(String::from("/home/user/x")))
+        );
+    }
+
+    #[test]
+    fn test_expand_without_interpolate() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        braced = "$${HOME}"
+        plain = "$$HOME"
+        "#,
+        )
+        .unwrap();
+
+        expand_env_with(&mut toml, &vars()).unwrap();
+        assert_eq!(
+            toml.read("braced").unwrap(),
+            Some(&Value::String(String::from("$${HOME}")))
+        );
+        assert_eq!(
+            toml.read("plain").unwrap(),
+            Some(&Value::String(String::from("$HOME")))
+        );
+    }
+
+    #[test]
+    fn test_expand_document_error() {
+        let mut toml: Value = toml_from_str("a = \"$HOME\"\n[b]\nc = \"$UNSET\"").unwrap();
+        let original = toml.clone();
+
+        let source = |name: &str| {
+            if name == "HOME" {
+                Some(String::from("/home/user"))
+            } else {
+                None
+            }
+        };
+
+        let err = expand_env_with(&mut toml, &source).unwrap_err();
+        assert_eq!(err.to_string(), "Variable 'UNSET' used at 'b.c' is not set");
+        assert_eq!(toml, original);
+    }
+}
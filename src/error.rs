@@ -16,9 +16,15 @@ pub enum Error {
     #[error("{}", _0)]
     TomlSerialize(#[from] ::toml::ser::Error),
 
+    /// Not returned anymore, deserialization errors are reported as `Error::Deserialize`
     #[cfg(feature = "typed")]
+    #[deprecated(note = "deserialization errors are reported as `Error::Deserialize`")]
     #[error("{}", _0)]
-    TomlDeserialize(#[from] ::toml::de::Error),
+    TomlDeserialize(::toml::de::Error),
+
+    #[cfg(feature = "typed")]
+    #[error("{}", located(.0, .1))]
+    Deserialize(String, String),
 
     // Errors for tokenizer
     #[error("Parsing the query '{0}' failed")]
@@ -72,4 +78,62 @@ pub enum Error {
 
     #[error("Value at '{0}' not there")]
     NotAvailable(String),
+
+    #[error("Value {1} at '{0}' is out of range {2}..={3}")]
+    OutOfRange(String, String, String, String),
+
+    #[error("Cannot parse '{1}' at '{0}' as {2}")]
+    UnitParseError(String, String, &'static str),
+
+    // Errors for schemas
+    #[error("Invalid pattern '{0}': {1}")]
+    InvalidPattern(String, String),
+
+    #[error("Invalid schema at '{0}': {1}")]
+    InvalidSchema(String, String),
+
+    // Errors for interpolation
+    #[error("Value at '{0}' references '{1}', which does not exist")]
+    MissingReference(String, String),
+
+    #[error("Reference cycle: {0}")]
+    ReferenceCycle(String),
+
+    #[error("Cannot interpolate value at '{0}': {1}")]
+    InterpolationError(String, String),
+
+    #[error("Variable '{1}' used at '{0}' is not set")]
+    UndefinedVariable(String, String),
+
+    // Errors for loading files
+    #[error("Cannot read '{0}': {1}")]
+    Io(String, #[source] ::std::io::Error),
+
+    #[error("Cannot parse '{0}': {1}")]
+    FileParseError(String, String),
+
+    #[error("Cannot parse document: {0}")]
+    ParseError(String),
+
+    #[error("Invalid include in '{0}': {1}")]
+    InvalidInclude(String, String),
+
+    #[error("Include cycle: {0}")]
+    IncludeCycle(String),
+
+    // Errors for patches
+    #[error("Patch test failed, value at '{0}' does not match")]
+    PatchTestFailed(String),
+
+    #[error("Cannot move '{0}' into its own child '{1}'")]
+    PatchMoveIntoChild(String, String),
+}
+
+#[cfg(feature = "typed")]
+fn located(path: &str, message: &str) -> String {
+    if path.is_empty() {
+        String::from(message)
+    } else {
+        format!("at {}: {}", path, message)
+    }
 }
@@ -0,0 +1,351 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Undo/redo history for documents
+//!
+//! A `History` owns a document and implements the set, insert and delete extension traits. Each
+//! mutation done through these traits is recorded, together with its inverse, so it can be
+//! undone and redone later.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::history::History;
+//! use toml_query::read::TomlValueReadExt;
+//! use toml_query::set::TomlValueSetExt;
+//!
+//! let toml : toml::Value = toml::from_str("a = 1").unwrap();
+//! let mut history = History::new(toml);
+//!
+//! history.set("a", toml::Value::Integer(2)).unwrap();
+//! assert!(history.undo().unwrap());
+//! assert_eq!(history.read("a").unwrap(), Some(&toml::Value::Integer(1)));
+//!
+//! assert!(history.redo().unwrap());
+//! assert_eq!(history.read("a").unwrap(), Some(&toml::Value::Integer(2)));
+//! ```
+
+use toml::Value;
+
+use crate::delete::TomlValueDeleteExt;
+use crate::error::Result;
+use crate::insert::TomlValueInsertExt;
+use crate::query::Query;
+use crate::set::TomlValueSetExt;
+use crate::undo::Undo;
+
+/// The kind of a recorded mutation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Set,
+    Insert,
+    Delete,
+}
+
+/// A recorded mutation of a document
+#[derive(Debug, Clone)]
+pub struct Change {
+    kind: ChangeKind,
+    query: Query,
+    value: Option<Value>,
+
+    // The query as passed by the user, which is needed for redoing the change
+    raw_query: String,
+    sep: char,
+
+    // Deleting nothing does not need to be undone
+    undo: Option<Undo>,
+}
+
+impl Change {
+    pub fn kind(&self) -> ChangeKind {
+        self.kind
+    }
+
+    /// The location of the change in the document
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// The value which was set or inserted, `None` for deletions
+    pub fn value(&self) -> Option<&Value> {
+        self.value.as_ref()
+    }
+}
+
+/// A document with an undo/redo history
+///
+/// The history dereferences to the current state of the document, `History::into_inner()`
+/// gives it up.
+#[derive(Debug, Clone)]
+pub struct History {
+    document: Value,
+    done: Vec<Change>,
+    undone: Vec<Change>,
+}
+
+impl History {
+    pub fn new(document: Value) -> Self {
+        History {
+            document,
+            done: vec![],
+            undone: vec![],
+        }
+    }
+
+    pub fn into_inner(self) -> Value {
+        self.document
+    }
+
+    /// The changes which can be undone, the most recent change last
+    pub fn done(&self) -> &[Change] {
+        &self.done
+    }
+
+    /// The changes which can be redone, the next change to redo last
+    pub fn undone(&self) -> &[Change] {
+        &self.undone
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Forget all recorded changes, keeping the document as it is
+    pub fn clear(&mut self) {
+        self.done.clear();
+        self.undone.clear();
+    }
+
+    /// Undo the most recent change
+    ///
+    /// Returns `Ok(false)` if there was nothing to undo. If undoing fails, the change stays the
+    /// most recent one.
+    pub fn undo(&mut self) -> Result<bool> {
+        let change = match self.done.pop() {
+            None => return Ok(false),
+            Some(change) => change,
+        };
+
+        if let Some(undo) = change.undo.clone() {
+            if let Err(e) = undo.apply(&mut self.document) {
+                self.done.push(change);
+                return Err(e);
+            }
+        }
+        self.undone.push(change);
+        Ok(true)
+    }
+
+    /// Redo the most recently undone change
+    ///
+    /// Returns `Ok(false)` if there was nothing to redo. If redoing fails, the change stays the
+    /// next one to redo.
+    pub fn redo(&mut self) -> Result<bool> {
+        let change = match self.undone.pop() {
+            None => return Ok(false),
+            Some(change) => change,
+        };
+
+        let performed = self.perform(
+            change.kind,
+            &change.raw_query,
+            change.sep,
+            change.value.clone(),
+        );
+        match performed {
+            Ok((_, undo)) => {
+                self.done.push(Change { undo, ..change });
+                Ok(true)
+            }
+            Err(e) => {
+                self.undone.push(change);
+                Err(e)
+            }
+        }
+    }
+
+    fn perform(
+        &mut self,
+        kind: ChangeKind,
+        query: &str,
+        sep: char,
+        value: Option<Value>,
+    ) -> Result<(Option<Value>, Option<Undo>)> {
+        let doc = &mut self.document;
+        match (kind, value) {
+            (ChangeKind::Set, Some(value)) => {
+                crate::undo::set(doc, query, sep, value).map(|(old, undo)| (old, Some(undo)))
+            }
+            (ChangeKind::Insert, Some(value)) => {
+                crate::undo::insert(doc, query, sep, value).map(|(old, undo)| (old, Some(undo)))
+            }
+            (ChangeKind::Delete, _) => crate::undo::delete(doc, query, sep),
+            (_, None) => unreachable!("Set and insert changes always carry a value"),
+        }
+    }
+
+    fn record(
+        &mut self,
+        kind: ChangeKind,
+        query: &str,
+        sep: char,
+        value: Option<Value>,
+    ) -> Result<Option<Value>> {
+        let parsed = Query::parse_with_seperator(query, sep)?;
+        let (old, undo) = self.perform(kind, query, sep, value.clone())?;
+
+        self.undone.clear();
+        self.done.push(Change {
+            kind,
+            query: parsed,
+            value,
+            raw_query: String::from(query),
+            sep,
+            undo,
+        });
+
+        Ok(old)
+    }
+}
+
+crate::util::impl_document_wrapper!(History);
+
+impl TomlValueSetExt for History {
+    fn set_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        self.record(ChangeKind::Set, query, sep, Some(value))
+    }
+}
+
+impl TomlValueInsertExt for History {
+    fn insert_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        self.record(ChangeKind::Insert, query, sep, Some(value))
+    }
+}
+
+impl TomlValueDeleteExt for History {
+    fn delete_with_seperator(&mut self, query: &str, sep: char) -> Result<Option<Value>> {
+        self.record(ChangeKind::Delete, query, sep, None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_undo_redo() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        a = 1
+        array = [1, 2, 3]
+        empty = []
+        "#,
+        )
+        .unwrap();
+
+        let mut history = History::new(toml.clone());
+        assert!(history.set("table.a", Value::Integer(2)).is_ok());
+        assert!(history.insert("table.array.[0]", Value::Integer(0)).is_ok());
+        assert!(history.delete("table.empty").is_ok());
+        let modified = (*history).clone();
+
+        assert!(history.undo().unwrap());
+        assert!(history.undo().unwrap());
+        assert!(history.undo().unwrap());
+        assert!(!history.undo().unwrap());
+        assert_eq!(*history, toml);
+
+        assert!(history.redo().unwrap());
+        assert!(history.redo().unwrap());
+        assert!(history.redo().unwrap());
+        assert!(!history.redo().unwrap());
+        assert_eq!(*history, modified);
+    }
+
+    #[test]
+    fn test_new_change_clears_redo() {
+        let mut history = History::new(toml_from_str("[table]\na = 1").unwrap());
+        assert!(history.set("table.a", Value::Integer(2)).is_ok());
+        assert!(history.undo().unwrap());
+        assert!(history.can_redo());
+
+        assert!(history.set("table.a", Value::Integer(3)).is_ok());
+        assert!(!history.can_redo());
+        assert_eq!(history.read("table.a").unwrap(), Some(&Value::Integer(3)));
+    }
+
+    #[test]
+    fn test_failed_change_is_not_recorded() {
+        let toml: Value = toml_from_str("[table]\na = 1").unwrap();
+        let mut history = History::new(toml.clone());
+        assert!(history.set("nonexistent.a", Value::Integer(2)).is_err());
+        assert!(history.insert("other.[0]", Value::Integer(2)).is_err());
+        assert!(!history.can_undo());
+        assert_eq!(*history, toml);
+    }
+
+    #[test]
+    fn test_failed_undo_and_redo_keep_change() {
+        let toml: Value = toml_from_str("[table]\na = 1").unwrap();
+        let mut history = History::new(toml.clone());
+        assert!(history.set("table.a", Value::Integer(2)).is_ok());
+
+        // the table the change was done in is gone, so neither undoing nor redoing can work
+        history.document = Value::Integer(0);
+        assert!(history.undo().is_err());
+        assert_eq!(history.done().len(), 1);
+        assert!(history.undone().is_empty());
+
+        history.document = toml.clone();
+        assert!(history.undo().unwrap());
+
+        history.document = Value::Integer(0);
+        assert!(history.redo().is_err());
+        assert!(history.done().is_empty());
+        assert_eq!(history.undone().len(), 1);
+
+        history.document = toml.clone();
+        assert!(history.redo().unwrap());
+        assert_eq!(history.read("table.a").unwrap(), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_inspect_history() {
+        let toml: Value = toml_from_str("[table]\na = 1").unwrap();
+        let mut history = History::new(toml.clone());
+        assert!(history.set("table.a", Value::Integer(2)).is_ok());
+        assert!(history.delete("table.nonexistent").is_ok());
+
+        let kinds = history.done().iter().map(Change::kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![ChangeKind::Set, ChangeKind::Delete]);
+        assert_eq!(history.done()[0].query().to_string(), "table.a");
+        assert_eq!(history.done()[0].value(), Some(&Value::Integer(2)));
+
+        assert!(history.undo().unwrap());
+        assert!(history.undo().unwrap());
+        assert_eq!(history.undone().len(), 2);
+        assert_eq!(history.into_inner(), toml);
+    }
+}
@@ -0,0 +1,494 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Loading documents which include other files
+//!
+//! A document can include other files with a top-level key, `include` by default:
+//!
+//! ```toml
+//! include = ["common.toml", "local/*.toml"]
+//!
+//! [server]
+//! port = 8080
+//! ```
+//!
+//! The included files are loaded relative to the including file and merged in order, the
+//! including file itself is merged last, so its values override the included ones. Tables are
+//! merged key by key, all other values (including arrays) are replaced. Included files can
+//! include other files themselves, include cycles are reported as errors.
+//!
+//! The file name of an include can contain the wildcards `*` and `?`, the matching files are
+//! included in alphabetical order. An include without wildcards has to exist.
+//!
+//! The resulting `Loaded` document remembers which file each value came from, and where in the
+//! file it is defined.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::location::{parse_file, Location, Locations};
+use crate::query::Query;
+
+/// The key which is used for includes by default
+pub const DEFAULT_INCLUDE_KEY: &str = "include";
+
+/// Loads documents and the files they include
+#[derive(Debug, Clone)]
+pub struct Loader {
+    key: String,
+}
+
+impl Default for Loader {
+    fn default() -> Self {
+        Loader::new()
+    }
+}
+
+impl Loader {
+    /// A loader which uses `DEFAULT_INCLUDE_KEY`
+    pub fn new() -> Self {
+        Loader {
+            key: String::from(DEFAULT_INCLUDE_KEY),
+        }
+    }
+
+    /// Use `key` for includes instead of `DEFAULT_INCLUDE_KEY`
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = key.into();
+        self
+    }
+
+    /// The key which is used for includes
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Load the document at `path` with all files it includes
+    ///
+    /// Returns `Error::Io` and `Error::FileParseError` if a file cannot be read or parsed,
+    /// `Error::InvalidInclude` if the include key does not contain a string or an array of
+    /// strings or names a file which does not exist, and `Error::IncludeCycle` if a file includes
+    /// itself, directly or indirectly.
+    pub fn load<P: AsRef<Path>>(&self, path: P) -> Result<Loaded> {
+        let mut stack = vec![];
+        self.load_file(path.as_ref(), &mut stack)
+    }
+
+    fn load_file(&self, path: &Path, stack: &mut Vec<PathBuf>) -> Result<Loaded> {
+        let canonical = fs::canonicalize(path).map_err(|e| io_error(path, e))?;
+
+        if let Some(pos) = stack.iter().position(|p| *p == canonical) {
+            let cycle = stack[pos..]
+                .iter()
+                .chain(Some(&canonical))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(Error::IncludeCycle(cycle));
+        }
+
+        let (mut document, locations) = parse_file(path)?;
+
+        let includes = match document {
+            Value::Table(ref mut tab) => tab.remove(&self.key),
+            _ => None,
+        };
+        let includes = match includes {
+            None => vec![],
+            Some(Value::String(s)) => vec![s],
+            Some(Value::Array(arr)) => arr
+                .into_iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s),
+                    _ => Err(invalid_include(
+                        path,
+                        "expected a string or an array of strings",
+                    )),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => {
+                return Err(invalid_include(
+                    path,
+                    "expected a string or an array of strings",
+                ))
+            }
+        };
+
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut loaded = Loaded::default();
+
+        stack.push(canonical);
+        for include in includes.iter() {
+            for file in resolve_include(base, include, path)? {
+                debug!("Including '{}' in '{}'", file.display(), path.display());
+                let included = self.load_file(&file, stack)?;
+                loaded.merge(included);
+            }
+        }
+        stack.pop();
+
+        loaded.merge(Loaded::from_file(document, locations));
+        Ok(loaded)
+    }
+}
+
+/// Load the document at `path` with `Loader::new()`
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Loaded> {
+    Loader::new().load(path)
+}
+
+fn io_error(path: &Path, e: ::std::io::Error) -> Error {
+    Error::Io(path.display().to_string(), e)
+}
+
+fn invalid_include(path: &Path, msg: &str) -> Error {
+    Error::InvalidInclude(path.display().to_string(), String::from(msg))
+}
+
+/// Find the files `include` refers to, relative to `base`
+fn resolve_include(base: &Path, include: &str, from: &Path) -> Result<Vec<PathBuf>> {
+    let path = base.join(include);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    if !name.contains(['*', '?']) {
+        if !path.is_file() {
+            let msg = format!("'{}' does not exist", include);
+            return Err(invalid_include(from, &msg));
+        }
+        return Ok(vec![path]);
+    }
+
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let mut files = vec![];
+    for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let entry = entry.map_err(|e| io_error(dir, e))?;
+        let matches = matches_wildcard(&name, &entry.file_name().to_string_lossy());
+        if matches && entry.path().is_file() {
+            files.push(entry.path());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Check whether `name` matches `pattern`, which can contain the wildcards `*` and `?`
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match pattern.split_first() {
+            None => name.is_empty(),
+            Some(('*', rest)) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+            Some(('?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+            Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+
+    matches(&pattern, &name)
+}
+
+/// A document which was loaded from one or more files
+#[derive(Debug, Clone)]
+pub struct Loaded {
+    document: Value,
+
+    /// The location of each value, tables have the location of their last definition
+    locations: BTreeMap<Query, Location>,
+}
+
+impl Default for Loaded {
+    fn default() -> Self {
+        Loaded {
+            document: Value::Table(Default::default()),
+            locations: BTreeMap::new(),
+        }
+    }
+}
+
+impl Loaded {
+    fn from_file(document: Value, locations: Locations) -> Self {
+        let locations = locations
+            .iter()
+            .filter(|(q, _)| !q.is_empty() && q.get(&document).is_some())
+            .map(|(q, l)| (q.clone(), l))
+            .collect();
+        Loaded {
+            document,
+            locations,
+        }
+    }
+
+    pub fn document(&self) -> &Value {
+        &self.document
+    }
+
+    pub fn into_document(self) -> Value {
+        self.document
+    }
+
+    /// The file the value at `query` came from
+    ///
+    /// Tables can be merged from several files, so they have no origin. Returns `Ok(None)` if
+    /// there is no such value.
+    pub fn origin(&self, query: &str) -> Result<Option<&Path>> {
+        let query = Query::parse(query)?;
+        if is_table(&self.document, &query) {
+            return Ok(None);
+        }
+        Ok(self.locations.get(&query).and_then(Location::file))
+    }
+
+    /// The files of all values which are neither tables nor inside arrays
+    pub fn origins(&self) -> impl Iterator<Item = (&Query, &Path)> {
+        self.locations
+            .iter()
+            .filter(move |(q, _)| {
+                let in_table = q
+                    .parent()
+                    .map(|p| is_table(&self.document, &p))
+                    .unwrap_or(true);
+                in_table && !is_table(&self.document, q)
+            })
+            .filter_map(|(q, l)| l.file().map(|file| (q, file)))
+    }
+
+    /// The location of the value at `query`, see `location::Locations::locate`
+    ///
+    /// The location of a table which is defined in several files is its definition in the file
+    /// which was merged last.
+    pub fn locate(&self, query: &str) -> Result<Option<&Location>> {
+        let query = Query::parse(query)?;
+        Ok(self.locations.get(&query))
+    }
+
+    /// The locations of all values, ordered by their queries
+    pub fn locations(&self) -> impl Iterator<Item = (&Query, &Location)> {
+        self.locations.iter()
+    }
+
+    /// Merge `other` into this document, the values of `other` win
+    fn merge(&mut self, other: Loaded) {
+        let Loaded {
+            document,
+            locations,
+        } = other;
+
+        // values which are replaced by `other` lose their locations, merged tables keep them
+        let replaced = locations
+            .keys()
+            .filter(|q| !(is_table(&document, q) && is_table(&self.document, q)))
+            .collect::<Vec<_>>();
+        self.locations
+            .retain(|query, _| !replaced.iter().any(|q| q.is_prefix_of(query)));
+
+        crate::merge::merge_values(&mut self.document, &document);
+        self.locations.extend(locations);
+    }
+}
+
+fn is_table(document: &Value, query: &Query) -> bool {
+    is_match!(query.get(document), Some(Value::Table(_)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+
+    /// A fresh directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!(
+            "toml-query-include-{}-{}",
+            ::std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_matches_wildcard() {
+        assert!(matches_wildcard("*.toml", "a.toml"));
+        assert!(matches_wildcard("a?.toml", "ab.toml"));
+        assert!(matches_wildcard("*", ""));
+        assert!(!matches_wildcard("*.toml", "a.json"));
+        assert!(!matches_wildcard("a?.toml", "a.toml"));
+    }
+
+    #[test]
+    fn test_load_includes() {
+        let dir = test_dir("includes");
+        write(
+            &dir,
+            "main.toml",
+            r#"
+        include = ["common.toml", "local/*.toml"]
+        name = "main"
+
+        [server]
+        port = 8080
+
+        [tls]
+        enabled = true
+        "#,
+        );
+        write(
+            &dir,
+            "common.toml",
+            r#"
+        name = "common"
+        list = [1, 2]
+
+        [server]
+        host = "localhost"
+        port = 80
+        "#,
+        );
+        write(
+            &dir,
+            "local/a.toml",
+            "list = [3]\ntls = false\n[server]\nhost = \"a\"",
+        );
+        write(&dir, "local/b.toml", "include = \"../nested.toml\"");
+        write(&dir, "nested.toml", "[client]\nretries = 3");
+        write(&dir, "local/ignored.json", "{}");
+
+        let loaded = load(dir.join("main.toml")).unwrap();
+        let doc = loaded.document();
+
+        assert!(doc.read("include").unwrap().is_none());
+        assert_eq!(
+            doc.read("name").unwrap(),
+            Some(&Value::String(String::from("main")))
+        );
+        assert_eq!(
+            doc.read("list").unwrap(),
+            Some(&Value::Array(vec![Value::Integer(3)]))
+        );
+        assert_eq!(
+            doc.read("server.host").unwrap(),
+            Some(&Value::String(String::from("a")))
+        );
+        assert_eq!(
+            doc.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert_eq!(
+            doc.read("client.retries").unwrap(),
+            Some(&Value::Integer(3))
+        );
+
+        let origin = |q: &str| loaded.origin(q).unwrap().map(Path::to_path_buf);
+        assert_eq!(origin("name"), Some(dir.join("main.toml")));
+        assert_eq!(origin("server.port"), Some(dir.join("main.toml")));
+        assert_eq!(origin("server.host"), Some(dir.join("local/a.toml")));
+        assert_eq!(origin("list.[0]"), Some(dir.join("local/a.toml")));
+        assert_eq!(
+            origin("client.retries"),
+            Some(dir.join("local/../nested.toml"))
+        );
+        assert_eq!(origin("tls.enabled"), Some(dir.join("main.toml")));
+        assert_eq!(origin("tls"), None);
+        assert_eq!(origin("server"), None);
+        assert_eq!(origin("nonexistent"), None);
+        assert_eq!(loaded.origins().count(), 6);
+
+        let port = loaded.locate("server.port").unwrap().unwrap();
+        assert_eq!(port.file(), Some(dir.join("main.toml").as_path()));
+        assert_eq!((port.line(), port.column()), (6, 16));
+        let server = loaded.locate("server").unwrap().unwrap();
+        assert_eq!(server.file(), Some(dir.join("main.toml").as_path()));
+        assert!(loaded.locate("tls").unwrap().is_some());
+        assert!(loaded.locate("tls.enabled").unwrap().is_some());
+        assert!(loaded.locate("list.[1]").unwrap().is_none());
+        assert!(loaded.locate("include").unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_custom_key() {
+        let dir = test_dir("custom-key");
+        write(&dir, "main.toml", "import = \"other.toml\"\ninclude = 1");
+        write(&dir, "other.toml", "a = 1");
+
+        let loaded = Loader::new()
+            .with_key("import")
+            .load(dir.join("main.toml"))
+            .unwrap();
+        assert_eq!(
+            loaded.document().read("a").unwrap(),
+            Some(&Value::Integer(1))
+        );
+        assert_eq!(
+            loaded.document().read("include").unwrap(),
+            Some(&Value::Integer(1))
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_cycle() {
+        let dir = test_dir("cycle");
+        write(&dir, "a.toml", "include = [\"b.toml\"]");
+        write(&dir, "b.toml", "include = [\"a.toml\"]");
+
+        let err = load(dir.join("a.toml")).unwrap_err();
+        assert!(is_match!(err, Error::IncludeCycle(..)));
+
+        write(&dir, "self.toml", "include = [\"self.toml\"]");
+        assert!(is_match!(
+            load(dir.join("self.toml")),
+            Err(Error::IncludeCycle(..))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = test_dir("errors");
+        write(&dir, "missing.toml", "include = [\"nonexistent.toml\"]");
+        write(&dir, "invalid.toml", "include = [1]");
+        write(&dir, "broken.toml", "a = ");
+
+        assert!(is_match!(
+            load(dir.join("missing.toml")),
+            Err(Error::InvalidInclude(..))
+        ));
+        assert!(is_match!(
+            load(dir.join("invalid.toml")),
+            Err(Error::InvalidInclude(..))
+        ));
+        assert!(is_match!(
+            load(dir.join("broken.toml")),
+            Err(Error::FileParseError(..))
+        ));
+        assert!(is_match!(
+            load(dir.join("nonexistent.toml")),
+            Err(Error::Io(..))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,11 +4,16 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 //
 
+#[cfg(feature = "typed")]
+use std::borrow::Borrow;
+
 #[cfg(feature = "typed")]
 use serde::Serialize;
 use toml::Value;
 
 use crate::error::{Error, Result};
+#[cfg(feature = "typed")]
+use crate::read::{partial_writes, Partial};
 use crate::tokenizer::tokenize_with_seperator;
 use crate::tokenizer::Token;
 
@@ -102,6 +107,50 @@ pub trait TomlValueInsertExt {
         let value = Value::try_from(value).map_err(Error::TomlSerialize)?;
         self.insert(query, value)
     }
+
+    /// Serialize a `Partial` and insert it at its location
+    ///
+    /// The value at `P::LOCATION` is replaced, intermediate tables are created. Fields with a
+    /// `#[location]` of their own are inserted at the first of their locations, fallback
+    /// locations are never written.
+    #[cfg(feature = "typed")]
+    fn insert_partial<'a, P: Partial<'a>>(&mut self, partial: &P::Output) -> Result<()> {
+        for (location, value) in partial_writes::<P>(partial)? {
+            self.insert(location, value)?;
+        }
+        Ok(())
+    }
+
+    /// Serialize a `Partial` and merge it into the document at its location
+    ///
+    /// Like `insert_partial()`, but the serialized values are merged into the values in the
+    /// document: tables key by key, so keys which are not part of the serialized `Partial` are
+    /// kept. Arrays and other values are replaced, empty tables are not written.
+    #[cfg(feature = "typed")]
+    fn merge_partial<'a, P: Partial<'a>>(&mut self, partial: &P::Output) -> Result<()>
+    where
+        Self: Borrow<Value>,
+    {
+        use crate::read::TomlValueReadExt;
+
+        for (location, value) in partial_writes::<P>(partial)? {
+            if is_match!(value, Value::Table(ref t) if t.is_empty()) {
+                continue;
+            }
+
+            let document: &Value = (*self).borrow();
+            let merged = match document.read(location)? {
+                Some(existing) => {
+                    let mut merged = existing.clone();
+                    crate::merge::merge_values(&mut merged, &value);
+                    merged
+                }
+                None => value,
+            };
+            self.insert(location, merged)?;
+        }
+        Ok(())
+    }
 }
 
 impl TomlValueInsertExt for Value {
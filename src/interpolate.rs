@@ -0,0 +1,310 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Interpolating values into strings
+//!
+//! A string value can reference other values of the same document with `${query}`, where
+//! `query` is a query string like `paths.base` or `servers.[0].host`. `interpolate()` replaces
+//! all references with the values they refer to. References in the referenced values are
+//! resolved as well, cycles are reported as errors. `$${` is a literal `${`.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::interpolate::interpolate;
+//! use toml_query::read::TomlValueReadTypeExt;
+//!
+//! let mut toml : toml::Value = toml::from_str(r#"
+//! base = "/srv/app"
+//! logs = "${base}/logs"
+//! access = "${logs}/access.log"
+//! "#).unwrap();
+//!
+//! interpolate(&mut toml).unwrap();
+//! assert_eq!(toml.read_string("access").unwrap().unwrap(), "/srv/app/logs/access.log");
+//! ```
+
+use std::collections::HashMap;
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::query::Query;
+use crate::walk::leaves;
+
+/// Resolve all `${query}` references in the string values of `document`
+///
+/// A string which consists of a single reference only takes the referenced value, so
+/// `"${server.port}"` becomes an integer if `server.port` is one. Otherwise the referenced values
+/// are formatted into the string. Tables and arrays cannot be referenced.
+///
+/// Returns `Error::MissingReference` if a referenced value does not exist,
+/// `Error::ReferenceCycle` if values reference each other and `Error::InterpolationError` for
+/// malformed references. The document is not modified if an error is returned.
+pub fn interpolate(document: &mut Value) -> Result<()> {
+    let paths = leaves(document)
+        .filter(|(_, value)| is_match!(value, Value::String(s) if s.contains("${")))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+
+    let resolved = {
+        let mut interpolator = Interpolator {
+            document,
+            resolved: HashMap::new(),
+            stack: vec![],
+        };
+
+        paths
+            .into_iter()
+            .map(|path| interpolator.resolve(&path).map(|value| (path, value)))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    for (path, value) in resolved {
+        if let Some(target) = path.get_mut(document) {
+            *target = value;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Part<'a> {
+    Literal(&'a str),
+    Reference(&'a str),
+}
+
+/// Split `s` into literal parts and references
+fn parse(s: &str) -> ::std::result::Result<Vec<Part<'_>>, String> {
+    let mut parts = vec![];
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            parts.push(Part::Literal(&rest[..start - 1]));
+            parts.push(Part::Literal("${"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unterminated reference in '{}'", s))?;
+        parts.push(Part::Literal(&rest[..start]));
+        parts.push(Part::Reference(&rest[start + 2..start + end]));
+        rest = &rest[start + end + 1..];
+    }
+
+    parts.push(Part::Literal(rest));
+    parts.retain(|p| *p != Part::Literal(""));
+    Ok(parts)
+}
+
+struct Interpolator<'doc> {
+    document: &'doc Value,
+    resolved: HashMap<Query, Value>,
+
+    /// The values which are currently being resolved, to detect cycles
+    stack: Vec<Query>,
+}
+
+impl<'doc> Interpolator<'doc> {
+    /// Resolve the references in the value at `path`
+    fn resolve(&mut self, path: &Query) -> Result<Value> {
+        if let Some(value) = self.resolved.get(path) {
+            return Ok(value.clone());
+        }
+
+        if let Some(pos) = self.stack.iter().position(|q| q == path) {
+            let cycle = self.stack[pos..]
+                .iter()
+                .chain(Some(path))
+                .map(|q| q.to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(Error::ReferenceCycle(cycle));
+        }
+
+        let s = match path.get(self.document) {
+            Some(Value::String(s)) => s,
+            Some(other) => return Ok(other.clone()),
+            None => return Err(Error::NotAvailable(path.to_string())),
+        };
+
+        self.stack.push(path.clone());
+        let value = self.expand(path, s);
+        self.stack.pop();
+
+        let value = value?;
+        self.resolved.insert(path.clone(), value.clone());
+        Ok(value)
+    }
+
+    fn expand(&mut self, path: &Query, s: &str) -> Result<Value> {
+        let parts = parse(s).map_err(|msg| Error::InterpolationError(path.to_string(), msg))?;
+
+        if let [Part::Reference(reference)] = parts[..] {
+            return self.lookup(path, reference);
+        }
+
+        let mut expanded = String::new();
+        for part in parts {
+            match part {
+                Part::Literal(literal) => expanded.push_str(literal),
+                Part::Reference(reference) => match self.lookup(path, reference)? {
+                    Value::String(s) => expanded.push_str(&s),
+                    other => expanded.push_str(&other.to_string()),
+                },
+            }
+        }
+
+        Ok(Value::String(expanded))
+    }
+
+    /// Get the resolved value `reference` in the value at `path` refers to
+    fn lookup(&mut self, path: &Query, reference: &str) -> Result<Value> {
+        let query = Query::parse(reference.trim()).map_err(|_| {
+            let msg = format!("invalid reference '{}'", reference);
+            Error::InterpolationError(path.to_string(), msg)
+        })?;
+
+        match query.get(self.document) {
+            None => Err(Error::MissingReference(
+                path.to_string(),
+                String::from(reference),
+            )),
+            Some(Value::String(_)) => self.resolve(&query),
+            Some(value @ Value::Table(_)) | Some(value @ Value::Array(_)) => {
+                let msg = format!(
+                    "'{}' refers to a {}",
+                    reference,
+                    crate::util::name_of_val(value)
+                );
+                Err(Error::InterpolationError(path.to_string(), msg))
+            }
+            Some(other) => Ok(other.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    fn string(s: &str) -> Value {
+        Value::String(String::from(s))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("a${b}c").unwrap(),
+            vec![Part::Literal("a"), Part::Reference("b"), Part::Literal("c")]
+        );
+        assert_eq!(parse("${b}").unwrap(), vec![Part::Reference("b")]);
+        assert_eq!(
+            parse("$${b}").unwrap(),
+            vec![Part::Literal("${"), Part::Literal("b}")]
+        );
+        assert!(parse("a${b").is_err());
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        host = "example.com"
+        url = "https://${host}:${server.port}/"
+        port = "${server.port}"
+        first = "${servers.[0]}"
+        literal = "$${host}"
+        servers = ["a"]
+
+        [server]
+        port = 8080
+
+        [client]
+        url = "${url}api"
+
+        [[list]]
+        name = "${ host }"
+        "#,
+        )
+        .unwrap();
+
+        interpolate(&mut toml).unwrap();
+
+        assert_eq!(
+            toml.read("url").unwrap(),
+            Some(&string("https://example.com:8080/"))
+        );
+        assert_eq!(toml.read("port").unwrap(), Some(&Value::Integer(8080)));
+        assert_eq!(toml.read("first").unwrap(), Some(&string("a")));
+        assert_eq!(toml.read("literal").unwrap(), Some(&string("${host}")));
+        assert_eq!(
+            toml.read("client.url").unwrap(),
+            Some(&string("https://example.com:8080/api"))
+        );
+        assert_eq!(
+            toml.read("list.[0].name").unwrap(),
+            Some(&string("example.com"))
+        );
+    }
+
+    #[test]
+    fn test_interpolate_missing_reference() {
+        let mut toml: Value = toml_from_str("a = \"x\"\nb = \"${c}\"").unwrap();
+        let original = toml.clone();
+
+        let err = interpolate(&mut toml).unwrap_err();
+        assert!(is_match!(err, Error::MissingReference(..)));
+        assert_eq!(
+            err.to_string(),
+            "Value at 'b' references 'c', which does not exist"
+        );
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_interpolate_cycle() {
+        let mut toml: Value = toml_from_str("a = \"${b}\"\nb = \"x${c}\"\nc = \"${b}\"").unwrap();
+
+        let err = interpolate(&mut toml).unwrap_err();
+        assert_eq!(err.to_string(), "Reference cycle: b -> c -> b");
+
+        let mut toml: Value = toml_from_str("a = \"${a}\"").unwrap();
+        assert!(is_match!(
+            interpolate(&mut toml),
+            Err(Error::ReferenceCycle(..))
+        ));
+    }
+
+    #[test]
+    fn test_interpolate_invalid() {
+        let mut toml: Value = toml_from_str("a = \"${t}\"\n[t]\nb = 1").unwrap();
+        let err = interpolate(&mut toml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Cannot interpolate value at 'a': 't' refers to a Table"
+        );
+
+        let mut toml: Value = toml_from_str("a = \"${t\"").unwrap();
+        assert!(is_match!(
+            interpolate(&mut toml),
+            Err(Error::InterpolationError(..))
+        ));
+
+        let mut toml: Value = toml_from_str("a = \"${}\"").unwrap();
+        assert!(is_match!(
+            interpolate(&mut toml),
+            Err(Error::InterpolationError(..))
+        ));
+    }
+}
@@ -0,0 +1,391 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Validating documents against JSON Schemas
+//!
+//! A subset of JSON Schema (draft 2020-12) is converted to a `schema::Schema`, so documents are
+//! validated the same way and violations are reported with queries. The supported keywords are
+//! `type`, `properties`, `required`, `items`, `enum`, `minimum`, `maximum`, `pattern` and
+//! `additionalProperties`, other keywords are ignored. The boolean schemas `true` and `false`
+//! accept and reject every value.
+//!
+//! As TOML has no `null`, the `null` type and `null` in `enum` are ignored. TOML datetimes are
+//! accepted where a string with the format `date-time`, `date`, `time` or `local-date-time` is
+//! expected.
+//!
+//! ```rust
+//! extern crate serde_json;
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::json_schema::validate_json_schema;
+//!
+//! let schema = serde_json::json!({
+//!     "type": "object",
+//!     "properties": {
+//!         "port": { "type": "integer", "minimum": 1 }
+//!     },
+//!     "required": ["port"]
+//! });
+//!
+//! let toml : toml::Value = toml::from_str("port = 0").unwrap();
+//! let violations = validate_json_schema(&toml, &schema).unwrap();
+//! assert_eq!(violations[0].to_string(), "at port: value 0 is out of range 1..");
+//! ```
+
+use serde_json::Map as JsonMap;
+use serde_json::Value as JsonValue;
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::query::{Query, Segment};
+use crate::schema::{validate, Rule, Schema, Violation};
+use crate::value::ValueType;
+
+const DATETIME_FORMATS: &[&str] = &["date-time", "date", "time", "local-date-time"];
+
+/// Convert a JSON Schema to a `Schema`
+///
+/// The rule for the root of the JSON Schema applies to the validated document itself. Returns
+/// `Error::InvalidSchema` if a supported keyword has an invalid value.
+pub fn from_json_schema(json: &JsonValue) -> Result<Schema> {
+    let mut schema = Schema::new();
+    schema.push(Query::default(), convert(json, "")?);
+    Ok(schema)
+}
+
+/// Validate `document` against the JSON Schema `json`
+///
+/// See `from_json_schema()` and `schema::validate()`.
+pub fn validate_json_schema(document: &Value, json: &JsonValue) -> Result<Vec<Violation>> {
+    from_json_schema(json).map(|schema| validate(document, &schema))
+}
+
+fn invalid(pointer: &str, msg: &str) -> Error {
+    Error::InvalidSchema(String::from(pointer), String::from(msg))
+}
+
+fn convert(json: &JsonValue, pointer: &str) -> Result<Rule> {
+    let obj = match json {
+        JsonValue::Bool(true) => return Ok(Rule::new()),
+        JsonValue::Bool(false) => return Ok(Rule::never()),
+        JsonValue::Object(obj) => obj,
+        _ => return Err(invalid(pointer, "expected an object or a boolean")),
+    };
+
+    let mut rule = Rule::any_of(&types(obj, pointer)?);
+
+    if let Some(min) = obj.get("minimum") {
+        let min = min
+            .as_f64()
+            .ok_or_else(|| invalid(pointer, "`minimum` must be a number"))?;
+        rule = rule.min(min);
+    }
+
+    if let Some(max) = obj.get("maximum") {
+        let max = max
+            .as_f64()
+            .ok_or_else(|| invalid(pointer, "`maximum` must be a number"))?;
+        rule = rule.max(max);
+    }
+
+    if let Some(allowed) = obj.get("enum") {
+        let allowed = allowed
+            .as_array()
+            .ok_or_else(|| invalid(pointer, "`enum` must be an array"))?;
+        rule = rule.one_of(allowed.iter().filter_map(to_toml).collect());
+    }
+
+    if let Some(pattern) = obj.get("pattern") {
+        let pattern = pattern
+            .as_str()
+            .ok_or_else(|| invalid(pointer, "`pattern` must be a string"))?;
+        rule = rule.pattern(pattern)?;
+    }
+
+    if let Some(items) = obj.get("items") {
+        rule = rule.items(convert(items, &format!("{}/items", pointer))?);
+    }
+
+    if ["properties", "required", "additionalProperties"]
+        .iter()
+        .any(|k| obj.contains_key(*k))
+    {
+        rule = rule.keys(keys(obj, pointer)?);
+    }
+
+    Ok(rule)
+}
+
+fn types(obj: &JsonMap<String, JsonValue>, pointer: &str) -> Result<Vec<ValueType>> {
+    let names = match obj.get("type") {
+        None => return Ok(vec![]),
+        Some(JsonValue::String(name)) => vec![name.as_str()],
+        Some(JsonValue::Array(names)) => names
+            .iter()
+            .map(|n| n.as_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid(pointer, "`type` must be a string or an array of strings"))?,
+        Some(_) => {
+            return Err(invalid(
+                pointer,
+                "`type` must be a string or an array of strings",
+            ))
+        }
+    };
+
+    let datetime = obj
+        .get("format")
+        .and_then(JsonValue::as_str)
+        .map(|f| DATETIME_FORMATS.contains(&f))
+        .unwrap_or(false);
+
+    let mut types = vec![];
+    for name in names.iter() {
+        match *name {
+            "string" if datetime => types.extend(&[ValueType::String, ValueType::Datetime]),
+            "string" => types.push(ValueType::String),
+            "integer" => types.push(ValueType::Integer),
+            "number" => types.extend(&[ValueType::Integer, ValueType::Float]),
+            "boolean" => types.push(ValueType::Boolean),
+            "array" => types.push(ValueType::Array),
+            "object" => types.push(ValueType::Table),
+            "null" => {}
+            _ => return Err(invalid(pointer, "unknown type")),
+        }
+    }
+
+    if types.is_empty() && !names.is_empty() {
+        return Err(invalid(pointer, "TOML has no `null` type"));
+    }
+
+    Ok(types)
+}
+
+fn keys(obj: &JsonMap<String, JsonValue>, pointer: &str) -> Result<Schema> {
+    let required = match obj.get("required") {
+        None => vec![],
+        Some(JsonValue::Array(names)) => names
+            .iter()
+            .map(|n| n.as_str())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid(pointer, "`required` must be an array of strings"))?,
+        Some(_) => return Err(invalid(pointer, "`required` must be an array of strings")),
+    };
+
+    let mut schema = Schema::new();
+    let key = |k: &str| Query::from(vec![Segment::Key(String::from(k))]);
+
+    match obj.get("properties") {
+        None => {}
+        Some(JsonValue::Object(properties)) => {
+            for (name, property) in properties.iter() {
+                let pointer = format!("{}/properties/{}", pointer, name);
+                let mut rule = convert(property, &pointer)?;
+                if required.contains(&name.as_str()) {
+                    rule = rule.required();
+                }
+                schema.push(key(name), rule);
+            }
+        }
+        Some(_) => return Err(invalid(pointer, "`properties` must be an object")),
+    }
+
+    for name in required.iter() {
+        let has_property = obj
+            .get("properties")
+            .and_then(JsonValue::as_object)
+            .map(|p| p.contains_key(*name))
+            .unwrap_or(false);
+        if !has_property {
+            schema.push(key(name), Rule::new().required());
+        }
+    }
+
+    match obj.get("additionalProperties") {
+        None | Some(JsonValue::Bool(true)) => {}
+        Some(JsonValue::Bool(false)) => schema = schema.deny_unknown_keys(),
+        Some(additional) => {
+            let pointer = format!("{}/additionalProperties", pointer);
+            schema = schema.other_keys(convert(additional, &pointer)?);
+        }
+    }
+
+    Ok(schema)
+}
+
+/// Convert a JSON value to a TOML value, `None` for `null`
+fn to_toml(json: &JsonValue) -> Option<Value> {
+    match json {
+        JsonValue::Null => None,
+        JsonValue::Bool(b) => Some(Value::Boolean(*b)),
+        JsonValue::Number(n) => n
+            .as_i64()
+            .map(Value::Integer)
+            .or_else(|| n.as_f64().map(Value::Float)),
+        JsonValue::String(s) => Some(Value::String(s.clone())),
+        JsonValue::Array(a) => Some(Value::Array(a.iter().filter_map(to_toml).collect())),
+        JsonValue::Object(o) => Some(Value::Table(
+            o.iter()
+                .filter_map(|(k, v)| to_toml(v).map(|v| (k.clone(), v)))
+                .collect(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use toml::from_str as toml_from_str;
+
+    fn schema() -> JsonValue {
+        json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string", "pattern": "^[a-z-]+$" },
+                "level": { "enum": ["debug", "info", null] },
+                "ratio": { "type": "number", "maximum": 1 },
+                "created": { "type": "string", "format": "date-time" },
+                "server": {
+                    "type": "object",
+                    "properties": {
+                        "port": { "type": "integer", "minimum": 1, "maximum": 65535 }
+                    },
+                    "required": ["host", "port"],
+                    "additionalProperties": false
+                },
+                "users": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "admin": { "type": "boolean" } }
+                    }
+                },
+                "labels": {
+                    "type": "object",
+                    "additionalProperties": { "type": "string" }
+                }
+            },
+            "required": ["name", "server"]
+        })
+    }
+
+    fn messages(violations: &[Violation]) -> Vec<String> {
+        violations.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_valid_document() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "my-app"
+        level = "info"
+        ratio = 1
+        created = 1979-05-27T07:32:00Z
+
+        [server]
+        host = "localhost"
+        port = 80
+
+        [[users]]
+        admin = true
+
+        [labels]
+        team = "core"
+        "#,
+        )
+        .unwrap();
+
+        assert!(validate_json_schema(&toml, &schema()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_document() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "My App"
+        level = "trace"
+        ratio = 1.5
+        created = 5
+
+        [server]
+        port = 0
+        tls = true
+
+        [[users]]
+        admin = "yes"
+
+        [labels]
+        team = 1
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            messages(&validate_json_schema(&toml, &schema()).unwrap()),
+            vec![
+                "at created: expected String or Datetime, found Integer",
+                "at labels.team: expected String, found Integer",
+                "at level: value is not one of the allowed values",
+                "at name: value does not match pattern '^[a-z-]+$'",
+                "at ratio: value 1.5 is out of range ..=1",
+                "at server.tls: unknown key",
+                "at server.port: value 0 is out of range 1..=65535",
+                "at server.host: required value is missing",
+                "at users.[0].admin: expected Boolean, found String",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_root_violation() {
+        let violations = validate_json_schema(&Value::Integer(1), &schema()).unwrap();
+        assert_eq!(messages(&violations), vec!["expected Table, found Integer"]);
+    }
+
+    #[test]
+    fn test_invalid_schema() {
+        let invalid = |json: JsonValue| from_json_schema(&json).unwrap_err();
+
+        let err = invalid(json!({ "properties": { "a": { "type": "foo" } } }));
+        assert_eq!(
+            err.to_string(),
+            "Invalid schema at '/properties/a': unknown type"
+        );
+
+        assert!(is_match!(
+            invalid(json!({ "type": "null" })),
+            Error::InvalidSchema(..)
+        ));
+        assert!(is_match!(
+            invalid(json!({ "minimum": "1" })),
+            Error::InvalidSchema(..)
+        ));
+        assert!(is_match!(
+            invalid(json!({ "pattern": "(" })),
+            Error::InvalidPattern(..)
+        ));
+        assert!(is_match!(invalid(json!(1)), Error::InvalidSchema(..)));
+    }
+
+    #[test]
+    fn test_false_schema() {
+        let schema = json!({ "properties": { "x": false } });
+
+        let toml: Value = toml_from_str("y = 1").unwrap();
+        assert!(validate_json_schema(&toml, &schema).unwrap().is_empty());
+
+        let toml: Value = toml_from_str("x = 1").unwrap();
+        assert_eq!(
+            messages(&validate_json_schema(&toml, &schema).unwrap()),
+            vec!["at x: no value is allowed here"]
+        );
+
+        let violations = validate_json_schema(&toml, &json!(false)).unwrap();
+        assert_eq!(messages(&violations), vec!["no value is allowed here"]);
+    }
+}
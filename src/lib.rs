@@ -31,6 +31,10 @@ extern crate serde_derive;
 #[macro_use]
 extern crate quickcheck;
 
+// lets the code generated by `#[derive(Partial)]` refer to `::toml_query` in our own tests
+#[cfg(all(test, feature = "typed"))]
+extern crate self as toml_query;
+
 // public modules
 
 #[cfg(not(feature = "log"))]
@@ -40,15 +44,36 @@ pub mod log;
 #[doc(hidden)]
 pub use toml_query_derive::*;
 
+pub mod coerce;
+pub mod collect;
+#[cfg(feature = "typed")]
+pub mod de;
 pub mod delete;
+pub mod env;
 pub mod error;
+pub mod history;
+pub mod include;
 pub mod insert;
+pub mod interpolate;
+#[cfg(feature = "json-schema")]
+pub mod json_schema;
+pub mod location;
+pub mod merge;
+pub mod observe;
+pub mod patch;
+pub mod query;
 pub mod read;
+pub mod redact;
+pub mod schema;
 pub mod set;
+pub mod transaction;
+pub mod units;
 mod util;
 pub mod value;
+pub mod walk;
 
 // private modules
 
 mod resolver;
 mod tokenizer;
+mod undo;
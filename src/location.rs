@@ -0,0 +1,323 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Source locations of values
+//!
+//! `toml::Value` does not know where in the text it was parsed from. `parse()` and `parse_file()`
+//! return a `Locations` table next to the document, which maps the queries of all values to
+//! their positions, so that errors can point to the right line:
+//!
+//! ```rust
+//! extern crate toml_query;
+//!
+//! use toml_query::location::parse;
+//!
+//! let (document, locations) = parse("[server]\nport = 70000").unwrap();
+//! let location = locations.locate("server.port").unwrap().unwrap();
+//! assert_eq!(format!("{}: port must be < 65536", location), "2:8: port must be < 65536");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use toml::de::{DeTable, DeValue};
+use toml::{Spanned, Value};
+
+use crate::error::{Error, Result};
+use crate::query::{Query, Segment};
+
+/// A position in a text, lines and columns start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// The span of a value in a text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// The byte offsets of the value
+    pub range: Range<usize>,
+
+    pub start: Position,
+
+    /// The position right after the value
+    pub end: Position,
+}
+
+/// Where a value is defined: the span of the value and the file it was parsed from, if any
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Location {
+    file: Option<PathBuf>,
+    span: Span,
+}
+
+impl Location {
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.start.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.span.start.column
+    }
+}
+
+impl fmt::Display for Location {
+    /// `file:line:column`, or `line:column` if there is no file
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(ref file) = self.file {
+            write!(f, "{}:", file.display())?;
+        }
+        write!(f, "{}", self.span.start)
+    }
+}
+
+/// The locations of all values of a parsed document
+#[derive(Debug, Clone, Default)]
+pub struct Locations {
+    file: Option<PathBuf>,
+    spans: BTreeMap<Query, Span>,
+}
+
+impl Locations {
+    /// The file the document was parsed from, if any
+    pub fn file(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    /// Get the location of the value at `query`
+    ///
+    /// Tables which are defined by a header like `[server]` are located at their header.
+    /// Returns `Ok(None)` if there is no such value.
+    pub fn locate(&self, query: &str) -> Result<Option<Location>> {
+        let query = Query::parse(query)?;
+        Ok(self.get(&query))
+    }
+
+    /// Get the location of the value at `query`, see `Locations::locate`
+    pub fn get(&self, query: &Query) -> Option<Location> {
+        self.spans.get(query).map(|span| Location {
+            file: self.file.clone(),
+            span: span.clone(),
+        })
+    }
+
+    /// The locations of all values, ordered by their queries
+    pub fn iter(&self) -> impl Iterator<Item = (&Query, Location)> {
+        self.spans
+            .keys()
+            .filter_map(move |q| self.get(q).map(|l| (q, l)))
+    }
+}
+
+/// Parse `text` into a document and the locations of its values
+///
+/// Returns `Error::ParseError` if `text` is not a valid TOML document.
+pub fn parse(text: &str) -> Result<(Value, Locations)> {
+    parse_located(text, None).map_err(Error::ParseError)
+}
+
+/// Read and parse the file at `path`, like `parse()`
+///
+/// The locations refer to `path`. Returns `Error::Io` if the file cannot be read and
+/// `Error::FileParseError` if it cannot be parsed.
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<(Value, Locations)> {
+    let path = path.as_ref();
+    let text =
+        ::std::fs::read_to_string(path).map_err(|e| Error::Io(path.display().to_string(), e))?;
+    parse_located(&text, Some(path))
+        .map_err(|msg| Error::FileParseError(path.display().to_string(), msg))
+}
+
+fn parse_located(
+    text: &str,
+    file: Option<&Path>,
+) -> ::std::result::Result<(Value, Locations), String> {
+    let document = toml::from_str::<Value>(text).map_err(|e| e.to_string())?;
+    let table = DeTable::parse(text).map_err(|e| e.to_string())?;
+
+    let lines = LineIndex::new(text);
+    let mut spans = BTreeMap::new();
+    record_table(table.get_ref(), &Query::default(), &lines, &mut spans);
+    spans.insert(Query::default(), lines.span(table.span()));
+
+    let locations = Locations {
+        file: file.map(Path::to_path_buf),
+        spans,
+    };
+    Ok((document, locations))
+}
+
+fn record_table(
+    table: &DeTable<'_>,
+    path: &Query,
+    lines: &LineIndex<'_>,
+    spans: &mut BTreeMap<Query, Span>,
+) {
+    for (key, value) in table.iter() {
+        let mut path = path.clone();
+        path.push(Segment::Key(key.get_ref().to_string()));
+        record_value(value, &path, lines, spans);
+    }
+}
+
+fn record_value(
+    value: &Spanned<DeValue<'_>>,
+    path: &Query,
+    lines: &LineIndex<'_>,
+    spans: &mut BTreeMap<Query, Span>,
+) {
+    spans.insert(path.clone(), lines.span(value.span()));
+
+    match value.get_ref() {
+        DeValue::Table(table) => record_table(table, path, lines, spans),
+        DeValue::Array(array) => {
+            for (idx, value) in array.iter().enumerate() {
+                let mut path = path.clone();
+                path.push(Segment::Index(idx));
+                record_value(value, &path, lines, spans);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts byte offsets to lines and columns
+struct LineIndex<'a> {
+    text: &'a str,
+
+    /// The byte offset at which each line starts
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let starts = Some(0)
+            .into_iter()
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, starts }
+    }
+
+    fn position(&self, offset: usize) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.starts[line];
+        let column = self
+            .text
+            .get(start..offset)
+            .map(|s| s.chars().count())
+            .unwrap_or(offset - start);
+
+        Position {
+            line: line + 1,
+            column: column + 1,
+        }
+    }
+
+    fn span(&self, range: Range<usize>) -> Span {
+        Span {
+            start: self.position(range.start),
+            end: self.position(range.end),
+            range,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TEXT: &str = r#"name = "app"
+ports = [80, 443]
+
+[server]
+host = "localhost"
+tls.enabled = true
+
+[[users]]
+name = "ä"
+roles = [{ id = 1 }]
+"#;
+
+    fn start(locations: &Locations, query: &str) -> (usize, usize) {
+        let location = locations.locate(query).unwrap().unwrap();
+        (location.line(), location.column())
+    }
+
+    #[test]
+    fn test_locate() {
+        let (document, locations) = parse(TEXT).unwrap();
+        assert!(document.get("server").is_some());
+
+        assert_eq!(start(&locations, "name"), (1, 8));
+        assert_eq!(start(&locations, "ports"), (2, 9));
+        assert_eq!(start(&locations, "ports.[1]"), (2, 14));
+        assert_eq!(start(&locations, "server.host"), (5, 8));
+        assert_eq!(start(&locations, "server"), (4, 1));
+        assert_eq!(start(&locations, "server.tls"), (6, 1));
+        assert_eq!(start(&locations, "users.[0]"), (8, 1));
+        assert_eq!(start(&locations, "server.tls.enabled"), (6, 15));
+        assert_eq!(start(&locations, "users.[0].roles"), (10, 9));
+        assert_eq!(start(&locations, "users.[0].roles.[0].id"), (10, 17));
+
+        let name = locations.locate("users.[0].name").unwrap().unwrap();
+        assert_eq!(name.span().start, Position { line: 9, column: 8 });
+        assert_eq!(
+            name.span().end,
+            Position {
+                line: 9,
+                column: 11
+            }
+        );
+        assert_eq!(&TEXT[name.span().range.clone()], "\"ä\"");
+
+        assert!(locations.locate("server.port").unwrap().is_none());
+        assert!(locations.locate("server.[0]").unwrap().is_none());
+        assert!(locations.iter().count() > 10);
+    }
+
+    #[test]
+    fn test_display() {
+        let (_, locations) = parse(TEXT).unwrap();
+        let location = locations.locate("server.host").unwrap().unwrap();
+        assert_eq!(location.to_string(), "5:8");
+        assert!(location.file().is_none());
+
+        let location = Location {
+            file: Some(PathBuf::from("config.toml")),
+            span: location.span().clone(),
+        };
+        assert_eq!(location.to_string(), "config.toml:5:8");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(is_match!(parse("a = "), Err(Error::ParseError(..))));
+        assert!(is_match!(
+            parse_file("/nonexistent/config.toml"),
+            Err(Error::Io(..))
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Merge patches
+//!
+//! Applying a partial document onto another document with the semantics of RFC 7386 (JSON Merge
+//! Patch). Because TOML does not have a `null` value, a designated marker value is used to
+//! remove keys from the target document.
+//!
+//! ```toml
+//! # base.toml
+//! [server]
+//! host = "localhost"
+//! port = 80
+//! debug = true
+//!
+//! # override.toml
+//! [server]
+//! port = 8080
+//! debug = "__delete__"
+//! ```
+//!
+//! Merging `override.toml` onto `base.toml` results in a `server` table with `host = "localhost"`
+//! and `port = 8080` and without the `debug` key.
+//!
+//! `fill_defaults()` merges the other way around: values from a defaults document are only added
+//! where the document has none.
+
+use toml::map::Map;
+use toml::Value;
+
+use crate::query::{Query, Segment};
+
+/// The marker which is used by `merge_patch()` to remove keys from the target document
+pub const DEFAULT_DELETE_MARKER: &str = "__delete__";
+
+/// Apply `patch` onto `document` as a merge patch, using `DEFAULT_DELETE_MARKER`
+///
+/// See documentation of `merge_patch_with_marker`
+pub fn merge_patch(document: &mut Value, patch: &Value) {
+    let marker = Value::String(String::from(DEFAULT_DELETE_MARKER));
+    merge_patch_with_marker(document, patch, &marker)
+}
+
+/// Apply `patch` onto `document` as a merge patch, using a custom marker for removing keys
+///
+/// # Semantics
+///
+/// * If `patch` is a table, each of its keys is merged into `document`:
+///     * If the value in `patch` is equal to `marker`, the key is removed from `document`
+///     * Otherwise, the value is merged recursively into the value in `document`, which is
+///       created if it does not exist
+/// * If `patch` is not a table, `document` is replaced by `patch`
+///
+/// If `document` is not a table but `patch` is, `document` is replaced by an empty table before
+/// merging. Arrays are never merged, but replaced as a whole.
+pub fn merge_patch_with_marker(document: &mut Value, patch: &Value, marker: &Value) {
+    if !is_match!(patch, Value::Table(_)) {
+        *document = patch.clone();
+        return;
+    }
+
+    if !is_match!(document, &mut Value::Table(_)) {
+        *document = Value::Table(Map::new());
+    }
+
+    merge_tables(
+        document,
+        patch,
+        &Query::default(),
+        &mut |tab, key, value, _| {
+            if value == marker {
+                trace!("Removing '{}' because of merge patch marker", key);
+                tab.remove(key);
+            } else {
+                let mut target = Value::Table(Map::new());
+                merge_patch_with_marker(&mut target, value, marker);
+                tab.insert(key.clone(), target);
+            }
+        },
+    );
+}
+
+/// Insert every value of `defaults` which is missing in `document`
+///
+/// Tables are filled recursively, existing values are never overwritten, even if they have a
+/// different type than the default. Arrays are not merged element-wise: an existing array is
+/// kept as it is. A table which is missing in `document` is inserted as a whole.
+///
+/// Returns the queries of the inserted values, in the order of `defaults`. Nothing is inserted if
+/// `document` or `defaults` is not a table.
+///
+/// ```rust
+/// extern crate toml;
+/// extern crate toml_query;
+///
+/// let mut toml : toml::Value = toml::from_str("[server]\nport = 8080").unwrap();
+/// let defaults : toml::Value = toml::from_str("[server]\nport = 80\nhost = \"localhost\"").unwrap();
+///
+/// let filled = toml_query::merge::fill_defaults(&mut toml, &defaults);
+/// assert_eq!(filled.len(), 1);
+/// assert_eq!(filled[0].to_string(), "server.host");
+/// ```
+pub fn fill_defaults(document: &mut Value, defaults: &Value) -> Vec<Query> {
+    let mut filled = vec![];
+    merge_tables(
+        document,
+        defaults,
+        &Query::default(),
+        &mut |tab, key, default, path| {
+            if !tab.contains_key(key) {
+                trace!("Filling in default for '{}'", path);
+                tab.insert(key.clone(), default.clone());
+                filled.push(path.clone());
+            }
+        },
+    );
+    filled
+}
+
+/// Merge `source` into `target`, keeping the values of `target` which `source` does not have
+///
+/// Tables are merged key by key, recursively. All other values of `source`, including arrays,
+/// replace the ones in `target`.
+pub(crate) fn merge_values(target: &mut Value, source: &Value) {
+    if !(is_match!(target, Value::Table(_)) && is_match!(source, Value::Table(_))) {
+        *target = source.clone();
+        return;
+    }
+
+    merge_tables(
+        target,
+        source,
+        &Query::default(),
+        &mut |tab, key, value, _| {
+            tab.insert(key.clone(), value.clone());
+        },
+    );
+}
+
+/// Walk the tables `target` and `source` side by side
+///
+/// Keys which are tables in both are walked recursively, `merge` is called for all other keys of
+/// `source`, with the table of `target` the key belongs to and the query of the key. Nothing
+/// happens if `target` or `source` is not a table.
+fn merge_tables<F>(target: &mut Value, source: &Value, path: &Query, merge: &mut F)
+where
+    F: FnMut(&mut Map<String, Value>, &String, &Value, &Query),
+{
+    let (tab, source) = match (target, source) {
+        (Value::Table(ref mut tab), Value::Table(ref source)) => (tab, source),
+        _ => return,
+    };
+
+    for (key, value) in source.iter() {
+        let mut path = path.clone();
+        path.push(Segment::Key(key.clone()));
+
+        match tab.get_mut(key) {
+            Some(existing @ Value::Table(_)) if is_match!(value, Value::Table(_)) => {
+                merge_tables(existing, value, &path, merge)
+            }
+            _ => merge(tab, key, value, &path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_merge_empty_patch() {
+        let mut toml: Value = toml_from_str("a = 1").unwrap();
+        let patch: Value = toml_from_str("").unwrap();
+
+        merge_patch(&mut toml, &patch);
+        assert_eq!(toml, toml_from_str::<Value>("a = 1").unwrap());
+    }
+
+    #[test]
+    fn test_merge_recursive() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        [server]
+        host = "localhost"
+        port = 80
+        debug = true
+        "#,
+        )
+        .unwrap();
+
+        let patch: Value = toml_from_str(
+            r#"
+        [server]
+        port = 8080
+        debug = "__delete__"
+
+        [client]
+        retries = 3
+        "#,
+        )
+        .unwrap();
+
+        merge_patch(&mut toml, &patch);
+
+        assert_eq!(
+            toml.read("server.host").unwrap(),
+            Some(&Value::String(String::from("localhost")))
+        );
+        assert_eq!(
+            toml.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert!(toml.read("server.debug").unwrap().is_none());
+        assert_eq!(
+            toml.read("client.retries").unwrap(),
+            Some(&Value::Integer(3))
+        );
+    }
+
+    #[test]
+    fn test_merge_replaces_arrays_and_values() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        list = [1, 2, 3]
+        value = 1
+        "#,
+        )
+        .unwrap();
+
+        let patch: Value = toml_from_str(
+            r#"
+        list = [4]
+        [value]
+        a = 1
+        "#,
+        )
+        .unwrap();
+
+        merge_patch(&mut toml, &patch);
+
+        assert_eq!(
+            toml.read("list").unwrap(),
+            Some(&Value::Array(vec![Value::Integer(4)]))
+        );
+        assert_eq!(toml.read("value.a").unwrap(), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_merge_marker_in_new_table() {
+        let mut toml: Value = toml_from_str("").unwrap();
+        let patch: Value = toml_from_str(
+            r#"
+        [table]
+        a = "__delete__"
+        b = 1
+        "#,
+        )
+        .unwrap();
+
+        merge_patch(&mut toml, &patch);
+
+        assert!(toml.read("table.a").unwrap().is_none());
+        assert_eq!(toml.read("table.b").unwrap(), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn test_merge_custom_marker() {
+        let mut toml: Value = toml_from_str("a = 1\nb = 2").unwrap();
+        let patch: Value = toml_from_str("a = false").unwrap();
+
+        merge_patch_with_marker(&mut toml, &patch, &Value::Boolean(false));
+
+        assert!(toml.read("a").unwrap().is_none());
+        assert_eq!(toml.read("b").unwrap(), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_fill_defaults() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        list = [1]
+        value = "set"
+
+        [server]
+        port = 8080
+        "#,
+        )
+        .unwrap();
+
+        let defaults: Value = toml_from_str(
+            r#"
+        list = [1, 2]
+        value = { a = 1 }
+
+        [server]
+        port = 80
+        host = "localhost"
+
+        [client]
+        retries = 3
+        "#,
+        )
+        .unwrap();
+
+        let filled = fill_defaults(&mut toml, &defaults);
+        let filled = filled.iter().map(|q| q.to_string()).collect::<Vec<_>>();
+        assert_eq!(filled, vec!["client", "server.host"]);
+
+        assert_eq!(
+            toml.read("list").unwrap(),
+            Some(&Value::Array(vec![Value::Integer(1)]))
+        );
+        assert_eq!(
+            toml.read("value").unwrap(),
+            Some(&Value::String(String::from("set")))
+        );
+        assert_eq!(
+            toml.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert_eq!(
+            toml.read("server.host").unwrap(),
+            Some(&Value::String(String::from("localhost")))
+        );
+        assert_eq!(
+            toml.read("client.retries").unwrap(),
+            Some(&Value::Integer(3))
+        );
+
+        assert!(fill_defaults(&mut toml, &defaults).is_empty());
+    }
+
+    #[test]
+    fn test_fill_defaults_not_a_table() {
+        let mut toml = Value::Integer(1);
+        let defaults: Value = toml_from_str("a = 1").unwrap();
+        assert!(fill_defaults(&mut toml, &defaults).is_empty());
+        assert_eq!(toml, Value::Integer(1));
+    }
+}
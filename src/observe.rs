@@ -0,0 +1,409 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Change notifications for documents
+//!
+//! An `Observable` owns a document and implements the set, insert and delete extension traits.
+//! Observers can be registered for a query prefix and are notified whenever a mutation done
+//! through these traits changes something at or below that prefix (or replaces a value above it).
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use std::cell::RefCell;
+//! use std::rc::Rc;
+//!
+//! use toml_query::observe::Observable;
+//! use toml_query::set::TomlValueSetExt;
+//!
+//! let toml : toml::Value = toml::from_str("[server]\nport = 80").unwrap();
+//! let mut doc = Observable::new(toml);
+//!
+//! let changes = Rc::new(RefCell::new(vec![]));
+//! let c = changes.clone();
+//! doc.watch("server", move |query: &_, _old: Option<&_>, _new: Option<&_>| {
+//!     c.borrow_mut().push(format!("{}", query));
+//! }).unwrap();
+//!
+//! doc.set("server.port", toml::Value::Integer(8080)).unwrap();
+//! assert_eq!(*changes.borrow(), vec![String::from("server.port")]);
+//! ```
+
+use std::fmt;
+
+use toml::Value;
+
+use crate::delete::TomlValueDeleteExt;
+use crate::error::Result;
+use crate::insert::TomlValueInsertExt;
+use crate::query::{Query, Segment};
+use crate::set::TomlValueSetExt;
+
+/// Something which is notified about changes in a document
+///
+/// This is implemented for all `FnMut(&Query, Option<&Value>, Option<&Value>)` closures.
+pub trait Observer {
+    /// Called after the value at `query` changed from `old` to `new`
+    ///
+    /// `old` is `None` if there was no value before, `new` is `None` if the value was removed.
+    fn changed(&mut self, query: &Query, old: Option<&Value>, new: Option<&Value>);
+}
+
+impl<F> Observer for F
+where
+    F: FnMut(&Query, Option<&Value>, Option<&Value>),
+{
+    fn changed(&mut self, query: &Query, old: Option<&Value>, new: Option<&Value>) {
+        self(query, old, new)
+    }
+}
+
+/// Identifies a registered observer, for removing it again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObserverId(usize);
+
+struct Watch {
+    id: ObserverId,
+    prefix: Query,
+    observer: Box<dyn Observer>,
+}
+
+/// A document which notifies observers about changes
+///
+/// Reading the document through `Deref` never notifies observers, only mutations do.
+pub struct Observable {
+    document: Value,
+    watches: Vec<Watch>,
+    next_id: usize,
+}
+
+impl Observable {
+    pub fn new(document: Value) -> Self {
+        Observable {
+            document,
+            watches: vec![],
+            next_id: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> Value {
+        self.document
+    }
+
+    /// Register an observer for all changes at or below `prefix`
+    ///
+    /// The observer is also notified if a value above `prefix` is changed, as that changes the
+    /// watched subtree as well. In this case, the query passed to the observer is the one of the
+    /// changed value, not `prefix`.
+    ///
+    /// Inserting into or deleting from an array moves the elements behind the changed index, so
+    /// these changes are reported for the whole array, with the query of the array. The same
+    /// goes for setting an index behind the end of an array, which appends the value.
+    pub fn watch<O>(&mut self, prefix: &str, observer: O) -> Result<ObserverId>
+    where
+        O: Observer + 'static,
+    {
+        self.watch_with_seperator(prefix, '.', observer)
+    }
+
+    /// Register an observer for all changes at or below `prefix`, using a custom seperator
+    ///
+    /// See documentation of `Observable::watch`
+    pub fn watch_with_seperator<O>(
+        &mut self,
+        prefix: &str,
+        sep: char,
+        observer: O,
+    ) -> Result<ObserverId>
+    where
+        O: Observer + 'static,
+    {
+        let prefix = Query::parse_with_seperator(prefix, sep)?;
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.watches.push(Watch {
+            id,
+            prefix,
+            observer: Box::new(observer),
+        });
+        Ok(id)
+    }
+
+    /// Remove a registered observer
+    ///
+    /// Returns whether the observer was registered.
+    pub fn unwatch(&mut self, id: ObserverId) -> bool {
+        let len = self.watches.len();
+        self.watches.retain(|w| w.id != id);
+        len != self.watches.len()
+    }
+
+    /// Apply `mutation` and notify the observers of the value at `query` if it changed
+    ///
+    /// If `query` refers to an array element, the whole array is compared and reported instead
+    /// if `shifting` is set, as the mutation moves the other elements, or if the element does not
+    /// exist, as the value is appended at another index then.
+    fn observed<F>(
+        &mut self,
+        query: &str,
+        sep: char,
+        shifting: bool,
+        mutation: F,
+    ) -> Result<Option<Value>>
+    where
+        F: FnOnce(&mut Value) -> Result<Option<Value>>,
+    {
+        let mut q = Query::parse_with_seperator(query, sep)?;
+        if is_match!(q.last(), Some(Segment::Index(_)))
+            && (shifting || q.get(&self.document).is_none())
+        {
+            q = q.parent().unwrap_or_default();
+        }
+
+        let old = q.get(&self.document).cloned();
+        let result = mutation(&mut self.document)?;
+        let new = q.get(&self.document);
+
+        if old.as_ref() != new {
+            for watch in self.watches.iter_mut() {
+                if watch.prefix.is_prefix_of(&q) || q.is_prefix_of(&watch.prefix) {
+                    watch.observer.changed(&q, old.as_ref(), new);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+impl fmt::Debug for Observable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Observable")
+            .field("document", &self.document)
+            .field("watches", &self.watches.len())
+            .finish()
+    }
+}
+
+crate::util::impl_document_wrapper!(Observable);
+
+impl TomlValueSetExt for Observable {
+    fn set_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        self.observed(query, sep, false, |doc| {
+            doc.set_with_seperator(query, sep, value)
+        })
+    }
+}
+
+impl TomlValueInsertExt for Observable {
+    fn insert_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        self.observed(query, sep, true, |doc| {
+            doc.insert_with_seperator(query, sep, value)
+        })
+    }
+}
+
+impl TomlValueDeleteExt for Observable {
+    fn delete_with_seperator(&mut self, query: &str, sep: char) -> Result<Option<Value>> {
+        self.observed(query, sep, true, |doc| {
+            doc.delete_with_seperator(query, sep)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use toml::from_str as toml_from_str;
+
+    type Log = Rc<RefCell<Vec<(String, Option<Value>, Option<Value>)>>>;
+
+    fn logger(log: &Log) -> impl Observer {
+        let log = log.clone();
+        move |q: &Query, old: Option<&Value>, new: Option<&Value>| {
+            log.borrow_mut()
+                .push((q.to_string(), old.cloned(), new.cloned()))
+        }
+    }
+
+    #[test]
+    fn test_notify_under_prefix() {
+        let log = Log::default();
+        let mut doc = Observable::new(
+            toml_from_str(
+                r#"
+        [server]
+        port = 80
+        empty = []
+
+        [client]
+        retries = 3
+        "#,
+            )
+            .unwrap(),
+        );
+        assert!(doc.watch("server", logger(&log)).is_ok());
+
+        assert!(doc.set("server.port", Value::Integer(8080)).is_ok());
+        assert!(doc.set("client.retries", Value::Integer(5)).is_ok());
+        assert!(doc.insert("server.host", Value::Integer(1)).is_ok());
+        assert!(doc.delete("server.empty").is_ok());
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (
+                    String::from("server.port"),
+                    Some(Value::Integer(80)),
+                    Some(Value::Integer(8080))
+                ),
+                (String::from("server.host"), None, Some(Value::Integer(1))),
+                (
+                    String::from("server.empty"),
+                    Some(Value::Array(vec![])),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notify_above_prefix() {
+        let log = Log::default();
+        let mut doc = Observable::new(
+            toml_from_str(
+                r#"
+        [server]
+        port = 80
+        empty = []
+
+        [client]
+        retries = 3
+        "#,
+            )
+            .unwrap(),
+        );
+        assert!(doc.watch("client.retries", logger(&log)).is_ok());
+
+        assert!(doc.set("client", Value::Integer(1)).is_ok());
+        assert_eq!(log.borrow().len(), 1);
+        assert_eq!(log.borrow()[0].0, "client");
+    }
+
+    #[test]
+    fn test_no_notification_without_change() {
+        let log = Log::default();
+        let mut doc = Observable::new(
+            toml_from_str(
+                r#"
+        [server]
+        port = 80
+        empty = []
+
+        [client]
+        retries = 3
+        "#,
+            )
+            .unwrap(),
+        );
+        assert!(doc.watch("server", logger(&log)).is_ok());
+
+        assert!(doc.set("server.port", Value::Integer(80)).is_ok());
+        assert!(doc.delete("server.nonexistent").is_ok());
+        assert!(doc.set("server.nonexistent.a", Value::Integer(1)).is_err());
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_notify_shifted_array() {
+        let log = Log::default();
+        let mut doc = Observable::new(toml_from_str("list = [1, 1]").unwrap());
+        assert!(doc.watch("list.[1]", logger(&log)).is_ok());
+
+        assert!(doc.insert("list.[0]", Value::Integer(1)).is_ok());
+        assert!(doc.delete("list.[0]").is_ok());
+
+        let one = Value::Integer(1);
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (
+                    String::from("list"),
+                    Some(Value::Array(vec![one.clone(), one.clone()])),
+                    Some(Value::Array(vec![one.clone(), one.clone(), one.clone()]))
+                ),
+                (
+                    String::from("list"),
+                    Some(Value::Array(vec![one.clone(), one.clone(), one.clone()])),
+                    Some(Value::Array(vec![one.clone(), one]))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notify_set_behind_array_end() {
+        let log = Log::default();
+        let mut doc = Observable::new(toml_from_str("list = [1]").unwrap());
+        assert!(doc.watch("list", logger(&log)).is_ok());
+
+        assert!(doc.set("list.[5]", Value::Integer(2)).is_ok());
+        assert!(doc.set("list.[0]", Value::Integer(3)).is_ok());
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                (
+                    String::from("list"),
+                    Some(Value::Array(vec![Value::Integer(1)])),
+                    Some(Value::Array(vec![Value::Integer(1), Value::Integer(2)]))
+                ),
+                (
+                    String::from("list.[0]"),
+                    Some(Value::Integer(1)),
+                    Some(Value::Integer(3))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unwatch() {
+        let log = Log::default();
+        let mut doc = Observable::new(
+            toml_from_str(
+                r#"
+        [server]
+        port = 80
+        empty = []
+
+        [client]
+        retries = 3
+        "#,
+            )
+            .unwrap(),
+        );
+        let id = doc.watch("server", logger(&log)).unwrap();
+
+        assert!(doc.unwatch(id));
+        assert!(!doc.unwatch(id));
+        assert!(doc.set("server.port", Value::Integer(8080)).is_ok());
+        assert!(log.borrow().is_empty());
+    }
+}
@@ -0,0 +1,367 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Patch documents
+//!
+//! A `Patch` is a list of `Operation`s which are applied to a document in order. The format is
+//! modeled after RFC 6902 (JSON Patch), but the locations in the document are addressed with
+//! toml-query queries (like `"foo.bar.[0]"`) rather than JSON pointers.
+//!
+//! With the `typed` feature enabled, a `Patch` can be (de)serialized, where each operation is a
+//! table with an `op` key:
+//!
+//! ```toml
+//! [[patch]]
+//! op = "replace"
+//! path = "server.port"
+//! value = 8080
+//!
+//! [[patch]]
+//! op = "move"
+//! from = "server.host"
+//! path = "server.address"
+//! ```
+
+#[cfg(feature = "typed")]
+use serde::{Deserialize, Serialize};
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::insert::TomlValueInsertExt;
+use crate::read::TomlValueReadExt;
+use crate::set::TomlValueSetExt;
+
+/// A single operation of a `Patch`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "typed", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "typed", serde(tag = "op", rename_all = "lowercase"))]
+pub enum Operation {
+    /// Insert `value` at `path`, creating intermediate tables
+    ///
+    /// See `TomlValueInsertExt::insert` for the semantics.
+    Add { path: String, value: Value },
+
+    /// Remove the value at `path`, which must exist
+    ///
+    /// Other than `TomlValueDeleteExt::delete`, this also removes non-empty tables and arrays.
+    Remove { path: String },
+
+    /// Replace the value at `path`, which must exist, with `value`
+    Replace { path: String, value: Value },
+
+    /// Remove the value at `from` and add it at `path`
+    Move { from: String, path: String },
+
+    /// Add a copy of the value at `from` at `path`
+    Copy { from: String, path: String },
+
+    /// Check that the value at `path` is equal to `value`
+    Test { path: String, value: Value },
+}
+
+/// A list of operations which are applied to a document
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "typed", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "typed", serde(transparent))]
+pub struct Patch(Vec<Operation>);
+
+impl Patch {
+    pub fn new() -> Self {
+        Patch(Vec::new())
+    }
+
+    /// Append an operation to the patch
+    pub fn push(&mut self, op: Operation) {
+        self.0.push(op)
+    }
+
+    pub fn operations(&self) -> &[Operation] {
+        &self.0
+    }
+}
+
+impl From<Vec<Operation>> for Patch {
+    fn from(ops: Vec<Operation>) -> Self {
+        Patch(ops)
+    }
+}
+
+/// Apply a patch to a document
+///
+/// # Semantics
+///
+/// The patch is applied atomically: either all operations succeed and the document is modified,
+/// or the first failing operation is returned as error and the document is left untouched.
+///
+/// # Return value
+///
+/// * If all operations were applied, `Ok(())` is returned
+/// * On failure, `Err(e)` is returned:
+///     * If a `test` operation does not match: `Error::PatchTestFailed`
+///     * If a `remove`, `replace`, `move` or `copy` operation refers to a value which is not
+///       present: `Error::NotAvailable`
+///     * If a `move` operation would move a value into one of its own children:
+///       `Error::PatchMoveIntoChild`
+///     * Errors from the underlying read/set/insert implementations
+///
+pub fn apply_patch(document: &mut Value, patch: &Patch) -> Result<()> {
+    let mut work = document.clone();
+    for op in patch.operations() {
+        apply_operation(&mut work, op)?;
+    }
+
+    *document = work;
+    Ok(())
+}
+
+fn apply_operation(document: &mut Value, op: &Operation) -> Result<()> {
+    trace!("Applying patch operation: {:?}", op);
+    match op {
+        Operation::Add { path, value } => document.insert(path, value.clone()).map(|_| ()),
+
+        Operation::Remove { path } => crate::util::remove_with_seperator(document, path, '.')?
+            .map(|_| ())
+            .ok_or_else(|| Error::NotAvailable(path.clone())),
+
+        Operation::Replace { path, value } => {
+            if document.read(path)?.is_none() {
+                return Err(Error::NotAvailable(path.clone()));
+            }
+            document.set(path, value.clone()).map(|_| ())
+        }
+
+        Operation::Move { from, path } => {
+            if from == path {
+                return Ok(());
+            }
+            if path.starts_with(&format!("{}.", from)) {
+                return Err(Error::PatchMoveIntoChild(from.clone(), path.clone()));
+            }
+
+            let value = crate::util::remove_with_seperator(document, from, '.')?
+                .ok_or_else(|| Error::NotAvailable(from.clone()))?;
+            document.insert(path, value).map(|_| ())
+        }
+
+        Operation::Copy { from, path } => {
+            let value = document
+                .read(from)?
+                .cloned()
+                .ok_or_else(|| Error::NotAvailable(from.clone()))?;
+            document.insert(path, value).map(|_| ())
+        }
+
+        Operation::Test { path, value } => match document.read(path)? {
+            Some(actual) if actual == value => Ok(()),
+            _ => Err(Error::PatchTestFailed(path.clone())),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_apply_empty_patch() {
+        let mut toml: Value = toml_from_str("[server]\nport = 80").unwrap();
+        let original = toml.clone();
+        assert!(apply_patch(&mut toml, &Patch::new()).is_ok());
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_apply_add_and_replace() {
+        let mut toml: Value = toml_from_str("[server]\nport = 80").unwrap();
+        let patch = Patch::from(vec![
+            Operation::Add {
+                path: String::from("client.retries"),
+                value: Value::Integer(3),
+            },
+            Operation::Replace {
+                path: String::from("server.port"),
+                value: Value::Integer(8080),
+            },
+        ]);
+
+        assert!(apply_patch(&mut toml, &patch).is_ok());
+        assert_eq!(
+            toml.read("client.retries").unwrap(),
+            Some(&Value::Integer(3))
+        );
+        assert_eq!(
+            toml.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+    }
+
+    #[test]
+    fn test_apply_replace_nonexistent() {
+        let mut toml: Value = toml_from_str("[server]\nport = 80").unwrap();
+        let patch = Patch::from(vec![Operation::Replace {
+            path: String::from("server.timeout"),
+            value: Value::Integer(1),
+        }]);
+
+        let res = apply_patch(&mut toml, &patch);
+        assert!(is_match!(res.unwrap_err(), Error::NotAvailable(_)));
+    }
+
+    #[test]
+    fn test_apply_remove_non_empty_table() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        [server]
+        host = "localhost"
+
+        [server.tls]
+        enabled = false
+        "#,
+        )
+        .unwrap();
+        let patch = Patch::from(vec![Operation::Remove {
+            path: String::from("server.tls"),
+        }]);
+
+        assert!(apply_patch(&mut toml, &patch).is_ok());
+        assert!(toml.read("server.tls").unwrap().is_none());
+        assert!(toml.read("server.host").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_apply_move_and_copy() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        [server]
+        host = "localhost"
+
+        [server.tls]
+        enabled = false
+        "#,
+        )
+        .unwrap();
+        let patch = Patch::from(vec![
+            Operation::Move {
+                from: String::from("server.host"),
+                path: String::from("server.address"),
+            },
+            Operation::Copy {
+                from: String::from("server.tls"),
+                path: String::from("client.tls"),
+            },
+        ]);
+
+        assert!(apply_patch(&mut toml, &patch).is_ok());
+        assert!(toml.read("server.host").unwrap().is_none());
+        assert_eq!(
+            toml.read("server.address").unwrap(),
+            Some(&Value::String(String::from("localhost")))
+        );
+        assert_eq!(
+            toml.read("client.tls.enabled").unwrap(),
+            Some(&Value::Boolean(false))
+        );
+        assert!(toml.read("server.tls.enabled").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_apply_move_into_child() {
+        let mut toml: Value = toml_from_str("[server]\nport = 80").unwrap();
+        let original = toml.clone();
+        let patch = Patch::from(vec![Operation::Move {
+            from: String::from("server"),
+            path: String::from("server.inner"),
+        }]);
+
+        let res = apply_patch(&mut toml, &patch);
+        assert!(is_match!(res.unwrap_err(), Error::PatchMoveIntoChild(_, _)));
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_apply_is_atomic() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        [server]
+        port = 80
+
+        [server.tls]
+        enabled = false
+        "#,
+        )
+        .unwrap();
+        let original = toml.clone();
+        let patch = Patch::from(vec![
+            Operation::Replace {
+                path: String::from("server.port"),
+                value: Value::Integer(8080),
+            },
+            Operation::Remove {
+                path: String::from("server.tls"),
+            },
+            Operation::Test {
+                path: String::from("server.port"),
+                value: Value::Integer(80),
+            },
+        ]);
+
+        let res = apply_patch(&mut toml, &patch);
+        assert!(is_match!(res.unwrap_err(), Error::PatchTestFailed(_)));
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_apply_add_below_missing_index() {
+        let mut toml: Value = toml_from_str("arr = [1]\nnum = 1").unwrap();
+        let original = toml.clone();
+
+        let patch = Patch::from(vec![Operation::Add {
+            path: String::from("arr.[5].x"),
+            value: Value::Integer(2),
+        }]);
+        let res = apply_patch(&mut toml, &patch);
+        assert!(is_match!(res.unwrap_err(), Error::IndexOutOfBounds(5, 1)));
+        assert_eq!(toml, original);
+
+        let patch = Patch::from(vec![Operation::Add {
+            path: String::from("num.x.y"),
+            value: Value::Integer(2),
+        }]);
+        let res = apply_patch(&mut toml, &patch);
+        assert!(is_match!(res.unwrap_err(), Error::QueryingValueAsTable(_)));
+        assert_eq!(toml, original);
+    }
+
+    #[cfg(feature = "typed")]
+    #[test]
+    fn test_deserialize_patch() {
+        #[derive(Deserialize)]
+        struct Doc {
+            patch: Patch,
+        }
+
+        let parsed: Doc = toml_from_str(
+            r#"
+        [[patch]]
+        op = "test"
+        path = "server.port"
+        value = 80
+
+        [[patch]]
+        op = "move"
+        from = "server.host"
+        path = "server.address"
+        "#,
+        )
+        .unwrap();
+
+        let mut toml: Value = toml_from_str("[server]\nhost = \"a\"\nport = 80").unwrap();
+        assert_eq!(parsed.patch.operations().len(), 2);
+        assert!(apply_patch(&mut toml, &parsed.patch).is_ok());
+        assert!(toml.read("server.address").unwrap().is_some());
+    }
+}
@@ -0,0 +1,231 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Parsed queries
+//!
+//! A `Query` is the owned, parsed form of a query string like `"foo.bar.[0]"`. It can be
+//! displayed as query string again, which can be passed to the extension traits.
+
+use std::fmt;
+use std::str::FromStr;
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::tokenizer::tokenize_with_seperator;
+use crate::tokenizer::Token;
+
+/// One element of a `Query`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Segment {
+    /// A key in a table
+    Key(String),
+
+    /// An index in an array
+    Index(usize),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Key(key) => write!(f, "{}", key),
+            Segment::Index(idx) => write!(f, "[{}]", idx),
+        }
+    }
+}
+
+/// A parsed query
+///
+/// The empty query refers to the document itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Query(Vec<Segment>);
+
+impl Query {
+    /// Parse a query string using a custom seperator
+    pub fn parse_with_seperator(query: &str, sep: char) -> Result<Query> {
+        let tokens = tokenize_with_seperator(query, sep)?;
+        let mut segments = vec![];
+        let mut token = Some(&tokens);
+
+        while let Some(tok) = token {
+            match tok {
+                Token::Identifier { ident, .. } => segments.push(Segment::Key(ident.clone())),
+                Token::Index { idx, .. } => segments.push(Segment::Index(*idx)),
+            }
+            token = tok.next();
+        }
+
+        Ok(Query(segments))
+    }
+
+    /// Parse a query string
+    pub fn parse(query: &str) -> Result<Query> {
+        Query::parse_with_seperator(query, '.')
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&Segment> {
+        self.0.last()
+    }
+
+    /// Append a segment to the query
+    pub fn push(&mut self, segment: Segment) {
+        self.0.push(segment)
+    }
+
+    /// Get the query which refers to `other`, relative to this query
+    pub fn join(&self, other: &Query) -> Query {
+        let mut segments = self.0.clone();
+        segments.extend(other.0.iter().cloned());
+        Query(segments)
+    }
+
+    /// Get the query without its last segment, if there is one
+    pub fn parent(&self) -> Option<Query> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(Query(self.0[..self.0.len() - 1].to_vec()))
+        }
+    }
+
+    /// Get the query which consists of the first `n` segments of this query
+    pub fn prefix(&self, n: usize) -> Query {
+        Query(self.0[..n.min(self.0.len())].to_vec())
+    }
+
+    /// Check whether `other` is equal to this query or refers to a value below it
+    pub fn is_prefix_of(&self, other: &Query) -> bool {
+        other.0.starts_with(&self.0)
+    }
+
+    /// Get the value this query refers to, if it is present in `document`
+    pub fn get<'doc>(&self, document: &'doc Value) -> Option<&'doc Value> {
+        self.0
+            .iter()
+            .try_fold(document, |val, segment| match (val, segment) {
+                (Value::Table(ref t), Segment::Key(ref key)) => t.get(key),
+                (Value::Array(ref a), Segment::Index(idx)) => a.get(*idx),
+                _ => None,
+            })
+    }
+
+    /// Get the value this query refers to mutably, if it is present in `document`
+    pub fn get_mut<'doc>(&self, document: &'doc mut Value) -> Option<&'doc mut Value> {
+        self.0
+            .iter()
+            .try_fold(document, |val, segment| match (val, segment) {
+                (Value::Table(ref mut t), Segment::Key(ref key)) => t.get_mut(key),
+                (Value::Array(ref mut a), Segment::Index(idx)) => a.get_mut(*idx),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Query> {
+        Query::parse(s)
+    }
+}
+
+impl From<Vec<Segment>> for Query {
+    fn from(segments: Vec<Segment>) -> Self {
+        Query(segments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_parse_and_display() {
+        let q = Query::parse("a.b.[1].c").unwrap();
+        assert_eq!(
+            q.segments(),
+            &[
+                Segment::Key(String::from("a")),
+                Segment::Key(String::from("b")),
+                Segment::Index(1),
+                Segment::Key(String::from("c")),
+            ]
+        );
+        assert_eq!(q.to_string(), "a.b.[1].c");
+    }
+
+    #[test]
+    fn test_parse_with_seperator() {
+        let q = Query::parse_with_seperator("a/[0]", '/').unwrap();
+        assert_eq!(q.to_string(), "a.[0]");
+    }
+
+    #[test]
+    fn test_parse_empty_query() {
+        assert!(is_match!(Query::parse(""), Err(Error::EmptyQueryError)));
+    }
+
+    #[test]
+    fn test_parent_and_prefix() {
+        let q = Query::parse("a.b.c").unwrap();
+        assert_eq!(q.parent().unwrap().to_string(), "a.b");
+        assert_eq!(q.prefix(1).to_string(), "a");
+        assert!(q.prefix(1).is_prefix_of(&q));
+        assert!(!q.is_prefix_of(&q.prefix(1)));
+        assert!(Query::default().parent().is_none());
+    }
+
+    #[test]
+    fn test_join() {
+        let a = Query::parse("a.[1]").unwrap();
+        let b = Query::parse("b.c").unwrap();
+        assert_eq!(a.join(&b).to_string(), "a.[1].b.c");
+        assert_eq!(Query::default().join(&b), b);
+    }
+
+    #[test]
+    fn test_get() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        array = [1, 2]
+        "#,
+        )
+        .unwrap();
+
+        let q = Query::parse("table.array.[1]").unwrap();
+        assert_eq!(q.get(&toml), Some(&Value::Integer(2)));
+
+        let q = Query::parse("table.array.[2]").unwrap();
+        assert!(q.get(&toml).is_none());
+
+        let q = Query::parse("table.array.a").unwrap();
+        assert!(q.get(&toml).is_none());
+
+        assert_eq!(Query::default().get(&toml), Some(&toml));
+    }
+}
@@ -6,12 +6,17 @@
 
 #[cfg(feature = "typed")]
 use std::fmt::Debug;
+use std::fmt::Display;
 
 #[cfg(feature = "typed")]
 use serde::{Deserialize, Serialize};
+use toml::map::Map;
+use toml::value::Datetime;
 use toml::Value;
 
 use crate::error::{Error, Result};
+#[cfg(feature = "typed")]
+use crate::query::{Query, Segment};
 use crate::tokenizer::tokenize_with_seperator;
 
 /// The Toml Read extensions
@@ -38,33 +43,291 @@ pub trait TomlValueReadExt<'doc> {
         self.read_mut_with_seperator(query, '.')
     }
 
+    /// Deserialize the value at `query` in place
+    ///
+    /// The value is not cloned, so `D` can borrow strings from the document.
     #[cfg(feature = "typed")]
-    fn read_deserialized<'de, D: Deserialize<'de>>(&'doc self, query: &str) -> Result<Option<D>> {
+    fn read_deserialized<D: Deserialize<'doc>>(&'doc self, query: &str) -> Result<Option<D>> {
         let raw = self.read(query)?;
 
         match raw {
             Some(value) => {
-                let deserialized = value.clone().try_into().map_err(Error::TomlDeserialize)?;
+                let deserialized =
+                    crate::de::from_value(value).map_err(|e| deserialize_error(query, e))?;
                 Ok(Some(deserialized))
             }
             None => Ok(None),
         }
     }
 
+    /// Read the part of the document described by `P`
+    ///
+    /// The value is read from `P::LOCATION`, or from the first of `P::FALLBACKS` which exists.
+    /// If `P::FIELD_LOCATIONS` is not empty, the value at each of these locations is put into the
+    /// table under the name of the field before deserializing, so that one struct can be
+    /// assembled from several sections of the document.
+    ///
+    /// Returns `Ok(None)` if none of the locations exists.
     #[cfg(feature = "typed")]
-    fn read_partial<'a, P: Partial<'a>>(&'doc self) -> Result<Option<P::Output>> {
-        self.read_deserialized::<P::Output>(P::LOCATION)
+    fn read_partial<P: Partial<'doc>>(&'doc self) -> Result<Option<P::Output>> {
+        if P::FIELD_LOCATIONS.is_empty() {
+            let locations = std::iter::once(P::LOCATION)
+                .chain(P::FALLBACKS.iter().cloned())
+                .filter(|l| !l.is_empty());
+            for location in locations {
+                if let Some(output) = self.read_deserialized::<P::Output>(location)? {
+                    return Ok(Some(output));
+                }
+            }
+            return Ok(None);
+        }
+
+        let mut assembly = match Assembly::of::<P, _>(self)? {
+            Some(assembly) => assembly,
+            None => return Ok(None),
+        };
+
+        crate::de::from_entries(::std::mem::take(&mut assembly.entries))
+            .map(Some)
+            .map_err(|e| assembly.error::<P>(e))
+    }
+}
+
+/// Deserialize `P` from `document` like `read_partial`, but check each field on its own
+///
+/// A field which fails to deserialize is left out and deserializing is retried, so that the
+/// errors of all fields are returned. A required field which is missing ends the search, as
+/// serde cannot continue without it. Returns `None` if the values cannot be assembled at all,
+/// for example because the section is not a table.
+#[cfg(feature = "typed")]
+pub(crate) fn partial_errors<'doc, P: Partial<'doc>>(document: &'doc Value) -> Option<Vec<Error>> {
+    let assembly = match Assembly::of::<P, _>(document) {
+        Ok(Some(assembly)) => assembly,
+        _ => return None,
+    };
+
+    let mut entries = assembly.entries.clone();
+    let mut left_out: Vec<String> = vec![];
+    let mut errors = vec![];
+    loop {
+        let e = match crate::de::from_entries::<P::Output>(entries.clone()) {
+            Ok(_) => return Some(errors),
+            Err(e) => e,
+        };
+
+        // the field is missing because it was left out, its error is already recorded
+        if is_match!(e.missing(), Some(m) if left_out.iter().any(|f| f == m)) {
+            return Some(errors);
+        }
+
+        let field = match e.path().first() {
+            Some(Segment::Key(key)) if entries.iter().any(|(k, _)| k == key) => Some(key.clone()),
+            _ => None,
+        };
+        errors.push(assembly.error::<P>(e));
+
+        match field {
+            Some(field) => {
+                entries.retain(|(k, _)| *k != field);
+                left_out.push(field);
+            }
+            None => return Some(errors),
+        }
+    }
+}
+
+/// The table entries a `Partial` is deserialized from
+#[cfg(feature = "typed")]
+struct Assembly<'doc> {
+    /// The location the section was read from, `None` if only fields were found
+    section: Option<&'static str>,
+
+    entries: Vec<(&'doc str, &'doc Value)>,
+
+    /// The fields which were read from locations of their own, with these locations
+    field_locations: Vec<(&'static str, &'static str)>,
+}
+
+#[cfg(feature = "typed")]
+impl<'doc> Assembly<'doc> {
+    /// Collect the entries for `P` from `reader`, `None` if none of the locations exists
+    fn of<P, R>(reader: &'doc R) -> Result<Option<Assembly<'doc>>>
+    where
+        P: Partial<'doc>,
+        R: TomlValueReadExt<'doc> + ?Sized,
+    {
+        let locations = std::iter::once(P::LOCATION)
+            .chain(P::FALLBACKS.iter().cloned())
+            .filter(|l| !l.is_empty());
+
+        let mut section = None;
+        let mut entries: Vec<(&'doc str, &'doc Value)> = vec![];
+        for location in locations {
+            match reader.read(location)? {
+                None => continue,
+                Some(Value::Table(t)) => entries.extend(t.iter().map(|(k, v)| (k.as_str(), v))),
+                Some(other) => {
+                    return Err(Error::TypeError("Table", crate::util::name_of_val(other)))
+                }
+            }
+            section = Some(location);
+            break;
+        }
+
+        let mut field_locations = vec![];
+        for field in P::FIELD_LOCATIONS {
+            for location in field.locations {
+                if let Some(value) = reader.read(location)? {
+                    entries.retain(|(k, _)| *k != field.field);
+                    entries.push((field.field, value));
+                    field_locations.push((field.field, *location));
+                    break;
+                }
+            }
+        }
+
+        if section.is_none() && field_locations.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Assembly {
+            section,
+            entries,
+            field_locations,
+        }))
+    }
+
+    /// Convert an error of deserializing the entries into an `Error` with the full path
+    fn error<P: Partial<'doc>>(&self, e: crate::de::Error) -> Error {
+        let field_location = match e.path().first() {
+            Some(Segment::Key(key)) => self.field_locations.iter().find(|(f, _)| f == key),
+            _ => None,
+        };
+        if let Some((_, location)) = field_location {
+            return deserialize_error(location, e.without_first());
+        }
+
+        // A missing field with a location of its own is missing there, not in the section
+        let missing = P::FIELD_LOCATIONS
+            .iter()
+            .filter(|_| e.path().is_empty())
+            .find(|f| e.missing() == Some(f.field))
+            .and_then(|f| f.locations.first());
+        match missing {
+            Some(location) => deserialize_error(location, e),
+            None => deserialize_error(self.section.unwrap_or(""), e),
+        }
     }
 }
 
+/// Convert a deserialization error for the value at `location` into an `Error`
+#[cfg(feature = "typed")]
+fn deserialize_error(location: &str, e: crate::de::Error) -> Error {
+    let prefix = Query::parse(location)
+        .map(|q| q.segments().to_vec())
+        .unwrap_or_default();
+    let (path, message) = e.prefixed(prefix).into_parts();
+    Error::Deserialize(path, message)
+}
+
 /// Describes a _part_ of a document
+///
+/// Usually this is implemented with `#[derive(Partial)]`:
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate toml;
+/// # extern crate toml_query;
+/// use serde::{Deserialize, Serialize};
+/// use toml_query::read::TomlValueReadExt;
+/// use toml_query::Partial;
+///
+/// #[derive(Partial, Serialize, Deserialize, Debug)]
+/// #[location("server", fallback = "legacy.server")]
+/// struct Server {
+///     port: u16,
+///
+///     #[location = "client.timeout"]
+///     timeout: u64,
+/// }
+///
+/// let toml : toml::Value = toml::from_str(r#"
+///     legacy.server.port = 80
+///     client.timeout = 30
+/// "#).unwrap();
+///
+/// let server = toml.read_partial::<Server>().unwrap().unwrap();
+/// assert_eq!(server.port, 80);
+/// assert_eq!(server.timeout, 30);
+/// ```
 #[cfg(feature = "typed")]
 pub trait Partial<'a> {
     // The location ("section") of the header where to find the struct
+    //
+    // An empty location means that the struct is assembled from `FIELD_LOCATIONS` only.
     const LOCATION: &'static str;
 
+    // Locations which are tried in order if there is nothing at `LOCATION`
+    const FALLBACKS: &'static [&'static str] = &[];
+
+    // Fields which are read from their own locations, relative to the document root
+    const FIELD_LOCATIONS: &'static [FieldLocation] = &[];
+
     // The type which represents the data
     type Output: Serialize + Deserialize<'a> + Debug;
+
+    /// The schema of the values at `LOCATION` and `FIELD_LOCATIONS`
+    ///
+    /// Empty by default. With `#[schema]` on the type, `#[derive(Partial)]` generates it from the
+    /// fields of the struct, their doc comments and serde defaults. The types of the fields have
+    /// to implement `schema::Describe` then.
+    fn schema() -> Result<crate::schema::Schema> {
+        Ok(crate::schema::Schema::new())
+    }
+}
+
+/// Serialize `partial` and split it into the values to write and the locations they belong to
+///
+/// Fields with a location of their own are written to the first of their locations, the remaining
+/// fields to `P::LOCATION`. If `P::LOCATION` is empty, the remaining fields are not written.
+#[cfg(feature = "typed")]
+pub(crate) fn partial_writes<'a, P: Partial<'a>>(
+    partial: &P::Output,
+) -> Result<Vec<(&'static str, Value)>> {
+    let value = Value::try_from(partial).map_err(Error::TomlSerialize)?;
+    if P::FIELD_LOCATIONS.is_empty() {
+        return Ok(vec![(P::LOCATION, value)]);
+    }
+
+    let mut table = match value {
+        Value::Table(table) => table,
+        other => return Err(Error::TypeError("Table", crate::util::name_of_val(&other))),
+    };
+
+    let mut writes = vec![];
+    for field in P::FIELD_LOCATIONS {
+        if let (Some(value), Some(location)) = (table.remove(field.field), field.locations.first())
+        {
+            writes.push((*location, value));
+        }
+    }
+
+    if !P::LOCATION.is_empty() {
+        writes.insert(0, (P::LOCATION, Value::Table(table)));
+    }
+
+    Ok(writes)
+}
+
+/// The location of a single field of a `Partial`
+#[cfg(feature = "typed")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLocation {
+    /// The name of the field, as seen by the deserializer
+    pub field: &'static str,
+
+    /// The locations to read the field from, the first one which exists is used
+    pub locations: &'static [&'static str],
 }
 
 impl<'doc> TomlValueReadExt<'doc> for Value {
@@ -85,13 +348,163 @@ impl<'doc> TomlValueReadExt<'doc> for Value {
     }
 }
 
+macro_rules! make_type_getter_variants {
+    ($fnname:ident, $or:ident, $or_else:ident, $require:ident, $rettype:ty) => {
+        #[doc = concat!("Like `", stringify!($fnname), "`, but returns `default` if there is no value")]
+        fn $or(&'doc self, query: &str, default: $rettype) -> Result<$rettype> {
+            self.$fnname(query).map(|o| o.unwrap_or(default))
+        }
+
+        #[doc = concat!("Like `", stringify!($fnname), "`, but calls `f` if there is no value")]
+        fn $or_else<F>(&'doc self, query: &str, f: F) -> Result<$rettype>
+        where
+            F: FnOnce() -> $rettype,
+        {
+            self.$fnname(query).map(|o| o.unwrap_or_else(f))
+        }
+
+        #[doc = concat!("Like `", stringify!($fnname), "`, but returns `Error::NotAvailable` if there is no value")]
+        fn $require(&'doc self, query: &str) -> Result<$rettype> {
+            self.$fnname(query)?
+                .ok_or_else(|| Error::NotAvailable(String::from(query)))
+        }
+    };
+}
+
 pub trait TomlValueReadTypeExt<'doc>: TomlValueReadExt<'doc> {
     fn read_string(&'doc self, query: &str) -> Result<Option<String>>;
     fn read_int(&'doc self, query: &str) -> Result<Option<i64>>;
     fn read_float(&'doc self, query: &str) -> Result<Option<f64>>;
     fn read_bool(&'doc self, query: &str) -> Result<Option<bool>>;
+    fn read_datetime(&'doc self, query: &str) -> Result<Option<Datetime>>;
+    fn read_array(&'doc self, query: &str) -> Result<Option<Vec<Value>>>;
+    fn read_table(&'doc self, query: &str) -> Result<Option<Map<String, Value>>>;
+
+    //
+    // Borrowing variants
+    //
+
+    fn read_str(&'doc self, query: &str) -> Result<Option<&'doc str>>;
+    fn read_array_ref(&'doc self, query: &str) -> Result<Option<&'doc [Value]>>;
+    fn read_table_ref(&'doc self, query: &str) -> Result<Option<&'doc Map<String, Value>>>;
+
+    //
+    // Variants with defaults and required values
+    //
+
+    make_type_getter_variants!(
+        read_string,
+        read_string_or,
+        read_string_or_else,
+        require_string,
+        String
+    );
+    make_type_getter_variants!(read_int, read_int_or, read_int_or_else, require_int, i64);
+    make_type_getter_variants!(
+        read_float,
+        read_float_or,
+        read_float_or_else,
+        require_float,
+        f64
+    );
+    make_type_getter_variants!(
+        read_bool,
+        read_bool_or,
+        read_bool_or_else,
+        require_bool,
+        bool
+    );
+    make_type_getter_variants!(
+        read_datetime,
+        read_datetime_or,
+        read_datetime_or_else,
+        require_datetime,
+        Datetime
+    );
+    make_type_getter_variants!(
+        read_array,
+        read_array_or,
+        read_array_or_else,
+        require_array,
+        Vec<Value>
+    );
+    make_type_getter_variants!(read_table, read_table_or, read_table_or_else, require_table, Map<String, Value>);
+    make_type_getter_variants!(
+        read_str,
+        read_str_or,
+        read_str_or_else,
+        require_str,
+        &'doc str
+    );
+    make_type_getter_variants!(
+        read_array_ref,
+        read_array_ref_or,
+        read_array_ref_or_else,
+        require_array_ref,
+        &'doc [Value]
+    );
+    make_type_getter_variants!(
+        read_table_ref,
+        read_table_ref_or,
+        read_table_ref_or_else,
+        require_table_ref,
+        &'doc Map<String, Value>
+    );
+
+    //
+    // Checked numeric narrowing
+    //
+
+    /// Read an integer and convert it to `N`
+    ///
+    /// If the integer does not fit into `N`, `Error::OutOfRange` is returned.
+    fn read_int_as<N: BoundedInteger>(&'doc self, query: &str) -> Result<Option<N>> {
+        match self.read_int(query)? {
+            None => Ok(None),
+            Some(i) => N::try_from(i).map(Some).map_err(|_| {
+                let (min, max) = (N::MIN.to_string(), N::MAX.to_string());
+                Error::OutOfRange(String::from(query), i.to_string(), min, max)
+            }),
+        }
+    }
+
+    /// Read a float and convert it to `f32`
+    ///
+    /// If the float is finite, but too large for `f32`, `Error::OutOfRange` is returned.
+    /// Precision which is lost in the conversion is not considered an error.
+    fn read_f32(&'doc self, query: &str) -> Result<Option<f32>> {
+        match self.read_float(query)? {
+            None => Ok(None),
+            Some(f) if f.is_finite() && f.abs() > f64::from(f32::MAX) => Err(Error::OutOfRange(
+                String::from(query),
+                f.to_string(),
+                f32::MIN.to_string(),
+                f32::MAX.to_string(),
+            )),
+            Some(f) => Ok(Some(f as f32)),
+        }
+    }
 }
 
+/// Integer types which `TomlValueReadTypeExt::read_int_as` can convert to
+pub trait BoundedInteger: TryFrom<i64> + Display {
+    const MIN: Self;
+    const MAX: Self;
+}
+
+macro_rules! impl_bounded_integer {
+    ($($t:ty),*) => {
+        $(
+            impl BoundedInteger for $t {
+                const MIN: Self = <$t>::MIN;
+                const MAX: Self = <$t>::MAX;
+            }
+        )*
+    };
+}
+
+impl_bounded_integer!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
 macro_rules! make_type_getter {
     ($fnname:ident, $rettype:ty, $typename:expr, $matcher:pat => $implementation:expr) => {
         fn $fnname(&'doc self, query: &str) -> Result<Option<$rettype>> {
@@ -112,6 +525,13 @@ where
     make_type_getter!(read_int, i64, "Integer", Some(&Value::Integer(obj)) => obj);
     make_type_getter!(read_float, f64, "Float", Some(&Value::Float(obj)) => obj);
     make_type_getter!(read_bool, bool, "Boolean", Some(&Value::Boolean(obj)) => obj);
+    make_type_getter!(read_datetime, Datetime, "Datetime", Some(&Value::Datetime(obj)) => obj);
+    make_type_getter!(read_array, Vec<Value>, "Array", Some(Value::Array(ref obj)) => obj.clone());
+    make_type_getter!(read_table, Map<String, Value>, "Table", Some(Value::Table(ref obj)) => obj.clone());
+
+    make_type_getter!(read_str, &'doc str, "String", Some(Value::String(ref obj)) => obj.as_str());
+    make_type_getter!(read_array_ref, &'doc [Value], "Array", Some(Value::Array(ref obj)) => obj.as_slice());
+    make_type_getter!(read_table_ref, &'doc Map<String, Value>, "Table", Some(Value::Table(ref obj)) => obj);
 }
 
 #[cfg(test)]
@@ -323,6 +743,145 @@ mod high_level_fn_test {
         assert_eq!(val.unwrap(), 1);
     }
 
+    #[test]
+    fn test_read_typed_values() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        s = "foo"
+        d = 1979-05-27T07:32:00Z
+        a = [1, 2]
+        "#,
+        )
+        .unwrap();
+
+        let val = toml.read_datetime("table.d").unwrap().unwrap();
+        assert_eq!(val.to_string(), "1979-05-27T07:32:00Z");
+
+        let val = toml.read_array("table.a").unwrap().unwrap();
+        assert_eq!(val, vec![Value::Integer(1), Value::Integer(2)]);
+
+        let val = toml.read_table("table").unwrap().unwrap();
+        assert_eq!(val.len(), 3);
+
+        assert_eq!(toml.read_str("table.s").unwrap(), Some("foo"));
+        assert_eq!(toml.read_array_ref("table.a").unwrap().unwrap().len(), 2);
+        assert!(toml
+            .read_table_ref("table")
+            .unwrap()
+            .unwrap()
+            .contains_key("s"));
+
+        assert!(toml.read_str("table.nonexistent").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_typed_values_type_error() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        a = 1
+        "#,
+        )
+        .unwrap();
+
+        let err = toml.read_datetime("table.a").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Datetime", "Integer")));
+
+        let err = toml.read_array_ref("table.a").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Array", "Integer")));
+
+        let err = toml.read_str("table").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("String", "Table")));
+    }
+
+    #[test]
+    fn test_read_or_defaults() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        a = 1
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml.read_int_or("table.a", 5).unwrap(), 1);
+        assert_eq!(toml.read_int_or("table.b", 5).unwrap(), 5);
+        assert_eq!(toml.read_str_or("table.b", "foo").unwrap(), "foo");
+        assert_eq!(
+            toml.read_string_or_else("table.b", || String::from("bar"))
+                .unwrap(),
+            "bar"
+        );
+
+        let err = toml.read_bool_or("table.a", true).unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Boolean", "Integer")));
+    }
+
+    #[test]
+    fn test_require() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        a = 1
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml.require_int("table.a").unwrap(), 1);
+
+        let err = toml.require_int("table.b").unwrap_err();
+        assert!(is_match!(err, Error::NotAvailable(_)));
+        assert_eq!(err.to_string(), "Value at 'table.b' not there");
+
+        let err = toml.require_table_ref("table.a").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Table", "Integer")));
+    }
+
+    #[test]
+    fn test_read_int_as() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        port = 8080
+        negative = -1
+        huge = 70000
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml.read_int_as::<u16>("table.port").unwrap(), Some(8080));
+        assert_eq!(toml.read_int_as::<usize>("table.port").unwrap(), Some(8080));
+        assert!(toml.read_int_as::<u16>("table.b").unwrap().is_none());
+
+        let err = toml.read_int_as::<u16>("table.huge").unwrap_err();
+        assert!(is_match!(err, Error::OutOfRange(..)));
+        assert_eq!(
+            err.to_string(),
+            "Value 70000 at 'table.huge' is out of range 0..=65535"
+        );
+
+        let err = toml.read_int_as::<u32>("table.negative").unwrap_err();
+        assert!(is_match!(err, Error::OutOfRange(..)));
+    }
+
+    #[test]
+    fn test_read_f32() {
+        let toml: Value = toml_from_str(
+            r#"
+        [table]
+        small = 0.5
+        huge = 1e300
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(toml.read_f32("table.small").unwrap(), Some(0.5));
+
+        let err = toml.read_f32("table.huge").unwrap_err();
+        assert!(is_match!(err, Error::OutOfRange(..)));
+    }
+
     #[cfg(feature = "typed")]
     #[test]
     fn test_name() {
@@ -369,6 +928,7 @@ mod high_level_fn_test {
 #[cfg(all(test, feature = "typed"))]
 mod partial_tests {
     use super::*;
+    use crate::Partial;
 
     use toml::map::Map;
     use toml::Value;
@@ -398,4 +958,395 @@ mod partial_tests {
         let obj: TestObj = tbl.read_partial::<TestObj>().unwrap().unwrap();
         assert_eq!(obj.value, "foobar");
     }
+
+    #[derive(Debug, Deserialize, Serialize, Partial)]
+    #[location("server", fallback = "legacy.server")]
+    struct Server {
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Partial)]
+    struct Assembled {
+        #[location = "server.port"]
+        port: u16,
+
+        #[location("client.name", fallback = "name")]
+        name: String,
+
+        #[serde(default)]
+        #[location = "client.retries"]
+        retries: Option<u8>,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, Partial)]
+    #[location = "server"]
+    struct Overridden {
+        port: u16,
+
+        #[location = "client.name"]
+        name: String,
+    }
+
+    #[test]
+    fn test_derive_constants() {
+        assert_eq!(Server::LOCATION, "server");
+        assert_eq!(Server::FALLBACKS, &["legacy.server"]);
+        assert!(Server::FIELD_LOCATIONS.is_empty());
+
+        assert_eq!(Assembled::LOCATION, "");
+        assert_eq!(
+            Assembled::FIELD_LOCATIONS[1],
+            FieldLocation {
+                field: "name",
+                locations: &["client.name", "name"],
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_partial_fallback() {
+        let toml: Value = toml::from_str("[legacy.server]\nport = 80").unwrap();
+        assert_eq!(toml.read_partial::<Server>().unwrap().unwrap().port, 80);
+
+        let toml: Value =
+            toml::from_str("[server]\nport = 8080\n[legacy.server]\nport = 80").unwrap();
+        assert_eq!(toml.read_partial::<Server>().unwrap().unwrap().port, 8080);
+
+        let toml: Value = toml::from_str("[client]\nport = 80").unwrap();
+        assert!(toml.read_partial::<Server>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_partial_field_locations() {
+        let toml: Value = toml::from_str(
+            r#"
+        name = "fallback"
+
+        [server]
+        port = 80
+
+        [client]
+        retries = 3
+        "#,
+        )
+        .unwrap();
+
+        let assembled = toml.read_partial::<Assembled>().unwrap().unwrap();
+        assert_eq!(assembled.port, 80);
+        assert_eq!(assembled.name, "fallback");
+        assert_eq!(assembled.retries, Some(3));
+
+        let empty = Value::Table(Map::new());
+        assert!(empty.read_partial::<Assembled>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_partial_field_location_overrides_section() {
+        let toml: Value = toml::from_str(
+            r#"
+        [server]
+        port = 80
+        name = "server"
+
+        [client]
+        name = "client"
+        "#,
+        )
+        .unwrap();
+
+        let obj = toml.read_partial::<Overridden>().unwrap().unwrap();
+        assert_eq!(obj.port, 80);
+        assert_eq!(obj.name, "client");
+    }
+
+    #[test]
+    fn test_read_partial_location_not_a_table() {
+        let toml: Value = toml::from_str("server = 1\n[client]\nname = \"a\"").unwrap();
+        let err = toml.read_partial::<Overridden>().unwrap_err();
+        assert!(is_match!(err, Error::TypeError("Table", "Integer")));
+    }
+
+    #[test]
+    fn test_write_partial_roundtrip() {
+        use crate::set::TomlValueSetExt;
+
+        let mut toml: Value = toml::from_str("[server]\nport = 80\nhost = \"localhost\"").unwrap();
+        let mut server = toml.read_partial::<Server>().unwrap().unwrap();
+        server.port = 8080;
+        assert!(toml.write_partial::<Server>(&server).is_ok());
+
+        assert_eq!(
+            toml.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert!(toml.read("server.host").unwrap().is_none());
+        assert!(toml.read("legacy").unwrap().is_none());
+
+        let mut empty = Value::Table(Map::new());
+        assert!(empty.write_partial::<Server>(&server).is_ok());
+        assert_eq!(
+            empty.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+    }
+
+    #[test]
+    fn test_write_partial_missing_parent() {
+        use crate::set::TomlValueSetExt;
+
+        let mut toml: Value = toml::from_str("[server]\nport = 1\nhost = \"h\"").unwrap();
+        let original = toml.clone();
+        let obj = Overridden {
+            port: 2,
+            name: String::from("client"),
+        };
+
+        let err = toml.write_partial::<Overridden>(&obj).unwrap_err();
+        assert!(is_match!(err, Error::IdentifierNotFoundInDocument(_)));
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_insert_partial_field_locations() {
+        use crate::insert::TomlValueInsertExt;
+
+        let mut toml = Value::Table(Map::new());
+        let obj = Overridden {
+            port: 80,
+            name: String::from("client"),
+        };
+        assert!(toml.insert_partial::<Overridden>(&obj).is_ok());
+
+        assert_eq!(toml.read("server.port").unwrap(), Some(&Value::Integer(80)));
+        assert!(toml.read("server.name").unwrap().is_none());
+        assert_eq!(
+            toml.read("client.name").unwrap(),
+            Some(&Value::String(String::from("client")))
+        );
+
+        let read = toml.read_partial::<Overridden>().unwrap().unwrap();
+        assert_eq!(read.port, 80);
+        assert_eq!(read.name, "client");
+    }
+
+    #[test]
+    fn test_merge_partial_keeps_other_keys() {
+        use crate::insert::TomlValueInsertExt;
+
+        let mut toml: Value = toml::from_str(
+            r#"
+        [server]
+        port = 80
+        host = "localhost"
+
+        [client]
+        name = "old"
+        retries = 1
+        "#,
+        )
+        .unwrap();
+
+        let assembled = Assembled {
+            port: 8080,
+            name: String::from("new"),
+            retries: None,
+        };
+        assert!(toml.merge_partial::<Assembled>(&assembled).is_ok());
+
+        assert_eq!(
+            toml.read("server.port").unwrap(),
+            Some(&Value::Integer(8080))
+        );
+        assert_eq!(
+            toml.read("server.host").unwrap(),
+            Some(&Value::String(String::from("localhost")))
+        );
+        assert_eq!(
+            toml.read("client.name").unwrap(),
+            Some(&Value::String(String::from("new")))
+        );
+        assert_eq!(
+            toml.read("client.retries").unwrap(),
+            Some(&Value::Integer(1))
+        );
+
+        let server = Server { port: 1 };
+        assert!(toml.merge_partial::<Server>(&server).is_ok());
+        assert_eq!(toml.read("server.port").unwrap(), Some(&Value::Integer(1)));
+        assert!(toml.read("server.host").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_merge_partial_into_history() {
+        use crate::history::History;
+        use crate::insert::TomlValueInsertExt;
+
+        let toml: Value = toml::from_str("[server]\nport = 80\nhost = \"localhost\"").unwrap();
+        let mut history = History::new(toml.clone());
+
+        assert!(history.merge_partial::<Server>(&Server { port: 1 }).is_ok());
+        assert_eq!(
+            history.read("server.port").unwrap(),
+            Some(&Value::Integer(1))
+        );
+        assert!(history.read("server.host").unwrap().is_some());
+
+        assert_eq!(history.done().len(), 1);
+        assert!(history.undo().unwrap());
+        assert_eq!(history.into_inner(), toml);
+    }
+
+    /// How to log
+    #[derive(Debug, Deserialize, Serialize, crate::Describe)]
+    #[serde(rename_all = "lowercase")]
+    enum Level {
+        Debug,
+        Info,
+    }
+
+    fn default_workers() -> u8 {
+        4
+    }
+
+    /// The server section
+    #[derive(Debug, Deserialize, Serialize, Partial)]
+    #[location = "server"]
+    #[schema]
+    struct Described {
+        /// The port to listen on
+        port: u16,
+
+        #[serde(rename = "log-level")]
+        level: Option<Level>,
+
+        #[serde(default = "default_workers")]
+        workers: u8,
+
+        #[serde(skip)]
+        #[allow(dead_code)]
+        cache: Vec<u8>,
+
+        /// The name of the client
+        #[location = "client.name"]
+        name: String,
+    }
+
+    #[test]
+    fn test_derive_schema() {
+        use crate::schema::{validate, Describe};
+        use crate::value::ValueType;
+
+        let schema = Described::schema().unwrap();
+        let rules = schema.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].0.to_string(), "server");
+        assert_eq!(rules[1].0.to_string(), "client.name");
+        assert_eq!(rules[1].1.description(), Some("The name of the client"));
+        assert!(rules[1].1.is_required());
+
+        let server = &rules[0].1;
+        assert_eq!(server.types(), &[ValueType::Table]);
+        assert_eq!(server.description(), Some("The server section"));
+
+        let keys = server.key_schema().unwrap().rules();
+        let names = keys.iter().map(|(q, _)| q.to_string()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["port", "log-level", "workers"]);
+        assert_eq!(keys[0].1.description(), Some("The port to listen on"));
+        assert_eq!(keys[0].1.range(), (Some(0.0), Some(65535.0)));
+        assert!(keys[0].1.is_required());
+        assert!(!keys[1].1.is_required());
+        assert_eq!(keys[1].1.description(), Some("How to log"));
+        assert!(!keys[2].1.is_required());
+        assert_eq!(keys[2].1.default_value(), Some(&Value::Integer(4)));
+
+        assert_eq!(Level::describe().allowed().len(), 2);
+        assert!(Server::schema().unwrap().rules().is_empty());
+
+        let toml: Value = toml::from_str(
+            r#"
+        [server]
+        port = 80
+        log-level = "trace"
+
+        [client]
+        "#,
+        )
+        .unwrap();
+        let violations = validate(&toml, &schema)
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            violations,
+            vec![
+                "at server.log-level: value is not one of the allowed values",
+                "at client.name: required value is missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_deserialized_error_path() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Web {
+            port: u16,
+        }
+
+        let toml: Value = toml::from_str(
+            r#"
+        [[servers.web]]
+        port = 80
+
+        [[servers.web]]
+        port = "8080"
+        "#,
+        )
+        .unwrap();
+
+        let err = toml
+            .read_deserialized::<Vec<Web>>("servers.web")
+            .unwrap_err();
+        assert!(is_match!(err, Error::Deserialize(..)));
+        assert_eq!(
+            err.to_string(),
+            "at servers.web.[1].port: invalid type: string \"8080\", expected u16"
+        );
+    }
+
+    #[test]
+    fn test_read_partial_error_path() {
+        let toml: Value = toml::from_str("[legacy.server]\nport = -1").unwrap();
+        let err = toml.read_partial::<Server>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at legacy.server.port: invalid value: integer `-1`, expected u16"
+        );
+
+        let toml: Value = toml::from_str("[server]\nport = true\n[client]\nname = 1").unwrap();
+        let err = toml.read_partial::<Assembled>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at server.port: invalid type: boolean `true`, expected u16"
+        );
+
+        let toml: Value = toml::from_str("[server]\nport = 1\n[client]\nname = 1").unwrap();
+        let err = toml.read_partial::<Overridden>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "at client.name: invalid type: integer `1`, expected a string"
+        );
+
+        let toml: Value = toml::from_str("[client]\nname = \"a\"").unwrap();
+        let err = toml.read_partial::<Assembled>().unwrap_err();
+        assert_eq!(err.to_string(), "at server.port: missing field `port`");
+
+        let toml: Value = toml::from_str("[server]\nport = 1").unwrap();
+        let err = toml.read_partial::<Overridden>().unwrap_err();
+        assert_eq!(err.to_string(), "at client.name: missing field `name`");
+
+        let toml: Value = toml::from_str("[server]\n[client]\nname = \"a\"").unwrap();
+        let err = toml.read_partial::<Overridden>().unwrap_err();
+        assert_eq!(err.to_string(), "at server: missing field `port`");
+    }
 }
@@ -0,0 +1,369 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Redacting secrets
+//!
+//! A `Redactor` masks all values whose queries match one of its patterns, so that documents can
+//! be logged or diffed without leaking credentials. Patterns are queries in which a segment can
+//! also be
+//!
+//! * `*`, which matches any single key or index, or
+//! * `**`, which matches any number of keys and indexes, including none.
+//!
+//! So `*.password` matches `db.password` but not `password` or `a.db.password`, while
+//! `**.token` matches `token` as well as `a.b.[0].token`.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::redact::Redactor;
+//!
+//! let toml : toml::Value = toml::from_str(r#"
+//! [db]
+//! user = "app"
+//! password = "hunter2"
+//! "#).unwrap();
+//!
+//! let redactor = Redactor::new(&["*.password"]).unwrap();
+//! let printed = redactor.display(&toml).to_string();
+//! assert!(printed.contains("user = \"app\""));
+//! assert!(!printed.contains("hunter2"));
+//! ```
+
+use std::fmt;
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::query::{Query, Segment};
+
+/// The value which replaces redacted values by default
+pub const DEFAULT_MASK: &str = "********";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PatternSegment {
+    Key(String),
+    Index(usize),
+
+    /// `*`
+    Any,
+
+    /// `**`
+    AnyDepth,
+}
+
+/// A query in which segments can be wildcards, see the module documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    source: String,
+    segments: Vec<PatternSegment>,
+}
+
+impl Pattern {
+    /// Parse a pattern
+    ///
+    /// Returns `Error::InvalidPattern` if a segment is empty or an index is not a number.
+    pub fn parse(pattern: &str) -> Result<Pattern> {
+        let segments = pattern
+            .split('.')
+            .map(|segment| match segment {
+                "" => Err(Error::InvalidPattern(
+                    String::from(pattern),
+                    String::from("empty segment"),
+                )),
+                "*" => Ok(PatternSegment::Any),
+                "**" => Ok(PatternSegment::AnyDepth),
+                s if s.starts_with('[') && s.ends_with(']') => s[1..s.len() - 1]
+                    .parse()
+                    .map(PatternSegment::Index)
+                    .map_err(|_| {
+                        Error::InvalidPattern(String::from(pattern), String::from("invalid index"))
+                    }),
+                s => Ok(PatternSegment::Key(String::from(s))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Pattern {
+            source: String::from(pattern),
+            segments,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Check whether `query` matches this pattern
+    pub fn matches(&self, query: &Query) -> bool {
+        matches(&self.segments, query.segments())
+    }
+}
+
+fn matches(pattern: &[PatternSegment], query: &[Segment]) -> bool {
+    match pattern.split_first() {
+        None => query.is_empty(),
+        Some((PatternSegment::AnyDepth, rest)) => {
+            (0..=query.len()).any(|i| matches(rest, &query[i..]))
+        }
+        Some((first, rest)) => match query.split_first() {
+            None => false,
+            Some((segment, query)) => {
+                let matching = match (first, segment) {
+                    (PatternSegment::Any, _) => true,
+                    (PatternSegment::Key(k), Segment::Key(key)) => k == key,
+                    (PatternSegment::Index(i), Segment::Index(idx)) => i == idx,
+                    _ => false,
+                };
+                matching && matches(rest, query)
+            }
+        },
+    }
+}
+
+/// Masks the values matching a list of patterns
+#[derive(Debug, Clone)]
+pub struct Redactor {
+    patterns: Vec<Pattern>,
+    mask: Value,
+}
+
+impl Redactor {
+    /// A redactor for `patterns`, which masks with `DEFAULT_MASK`
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| Pattern::parse(p.as_ref()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Redactor {
+            patterns,
+            mask: Value::String(String::from(DEFAULT_MASK)),
+        })
+    }
+
+    /// Replace redacted values with `mask` instead of `DEFAULT_MASK`
+    pub fn with_mask(mut self, mask: Value) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.patterns
+    }
+
+    /// Check whether the value at `query` is redacted
+    pub fn is_redacted(&self, query: &Query) -> bool {
+        self.patterns.iter().any(|p| p.matches(query))
+    }
+
+    /// Get a copy of `document` with all matching values masked
+    ///
+    /// A matching table or array is masked as a whole.
+    pub fn redact(&self, document: &Value) -> Value {
+        let mut redacted = document.clone();
+        self.redact_in_place(&mut redacted);
+        redacted
+    }
+
+    /// Mask all matching values in `document`
+    ///
+    /// Returns the queries of the masked values.
+    pub fn redact_in_place(&self, document: &mut Value) -> Vec<Query> {
+        let mut redacted = vec![];
+        self.redact_at(document, &Query::default(), &mut redacted);
+        redacted
+    }
+
+    /// Display `document` as TOML with all matching values masked
+    ///
+    /// Tables are printed as TOML documents, other values as they would appear on the right hand
+    /// side of a key.
+    pub fn display<'a>(&'a self, document: &'a Value) -> Redacted<'a> {
+        Redacted {
+            redactor: self,
+            document,
+        }
+    }
+
+    fn redact_at(&self, value: &mut Value, path: &Query, redacted: &mut Vec<Query>) {
+        if !path.is_empty() && self.is_redacted(path) {
+            *value = self.mask.clone();
+            redacted.push(path.clone());
+            return;
+        }
+
+        match value {
+            Value::Table(tab) => {
+                for (key, value) in tab.iter_mut() {
+                    let mut path = path.clone();
+                    path.push(Segment::Key(key.clone()));
+                    self.redact_at(value, &path, redacted);
+                }
+            }
+            Value::Array(arr) => {
+                for (idx, value) in arr.iter_mut().enumerate() {
+                    let mut path = path.clone();
+                    path.push(Segment::Index(idx));
+                    self.redact_at(value, &path, redacted);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A document which is displayed with its secrets masked, see `Redactor::display`
+#[derive(Debug)]
+pub struct Redacted<'a> {
+    redactor: &'a Redactor,
+    document: &'a Value,
+}
+
+impl fmt::Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.redactor.redact(self.document) {
+            table @ Value::Table(_) => {
+                let printed = toml::to_string(&table).map_err(|_| fmt::Error)?;
+                f.write_str(&printed)
+            }
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    fn query(q: &str) -> Query {
+        Query::parse(q).unwrap()
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        let single = Pattern::parse("*.password").unwrap();
+        assert!(single.matches(&query("db.password")));
+        assert!(!single.matches(&query("password")));
+        assert!(!single.matches(&query("a.db.password")));
+
+        let deep = Pattern::parse("**.token").unwrap();
+        assert!(deep.matches(&query("token")));
+        assert!(deep.matches(&query("services.[0].auth.token")));
+        assert!(!deep.matches(&query("token.a")));
+
+        let index = Pattern::parse("services.[1].*").unwrap();
+        assert!(index.matches(&query("services.[1].name")));
+        assert!(!index.matches(&query("services.[0].name")));
+
+        let any = Pattern::parse("**").unwrap();
+        assert!(any.matches(&query("a.[0].b")));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(is_match!(
+            Pattern::parse("a..b"),
+            Err(Error::InvalidPattern(..))
+        ));
+        assert!(is_match!(
+            Pattern::parse("a.[x]"),
+            Err(Error::InvalidPattern(..))
+        ));
+        assert!(is_match!(
+            Redactor::new(&[""]),
+            Err(Error::InvalidPattern(..))
+        ));
+    }
+
+    #[test]
+    fn test_redact() {
+        let toml: Value = toml_from_str(
+            r#"
+        password = "top"
+        token = "t0"
+
+        [db]
+        user = "app"
+        password = "hunter2"
+
+        [[services]]
+        name = "a"
+        auth = { token = "t1", scopes = ["read"] }
+
+        [secrets]
+        a = 1
+        b = [1, 2]
+        "#,
+        )
+        .unwrap();
+        let redactor = Redactor::new(&["*.password", "**.token", "secrets"]).unwrap();
+        let mask = Value::String(String::from(DEFAULT_MASK));
+
+        let redacted = redactor.redact(&toml);
+        assert_eq!(redacted.read("db.password").unwrap(), Some(&mask));
+        assert_eq!(redacted.read("token").unwrap(), Some(&mask));
+        assert_eq!(
+            redacted.read("services.[0].auth.token").unwrap(),
+            Some(&mask)
+        );
+        assert_eq!(redacted.read("secrets").unwrap(), Some(&mask));
+        assert_eq!(
+            redacted.read("password").unwrap(),
+            Some(&Value::String(String::from("top")))
+        );
+        assert_eq!(
+            redacted.read("services.[0].auth.scopes.[0]").unwrap(),
+            Some(&Value::String(String::from("read")))
+        );
+
+        let mut toml = toml;
+        let paths = redactor
+            .with_mask(Value::Boolean(false))
+            .redact_in_place(&mut toml);
+        let paths = paths.iter().map(|q| q.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec!["db.password", "secrets", "services.[0].auth.token", "token"]
+        );
+        assert_eq!(toml.read("token").unwrap(), Some(&Value::Boolean(false)));
+    }
+
+    #[test]
+    fn test_display() {
+        let toml: Value = toml_from_str(
+            r#"
+        password = "top"
+
+        [db]
+        password = "hunter2"
+        user = "app"
+
+        [[services]]
+        auth = { token = "t1" }
+        "#,
+        )
+        .unwrap();
+        let redactor = Redactor::new(&["**.password", "**.token"]).unwrap();
+        let printed = redactor.display(&toml).to_string();
+
+        let expected = r#"password = "********"
+
+[db]
+password = "********"
+user = "app"
+
+[[services]]
+
+[services.auth]
+token = "********"
+"#;
+        assert_eq!(printed, expected);
+        assert_eq!(redactor.display(&Value::Integer(1)).to_string(), "1");
+    }
+}
@@ -19,10 +19,8 @@ pub fn resolve<'doc>(toml: &'doc mut Value, tokens: &Token) -> Result<&'doc mut
     //  3. Identifier, toml: array                      -> error
     //  4. Index, toml: table                           -> error
     //  5. Index, toml: array, idx present              -> traverse
-    //  6. Index, toml: array, idx not present
-    //      6.1 -> next token is ident                  -> push Table
-    //      6.2 -> next token is index                  -> push Array
-    //      then traverse
+    //  6. Index, toml: array, idx not present          -> error
+    //  7. Identifier or Index, toml: other value       -> error
 
     match *tokens {
         Token::Identifier { ref ident, .. } => match toml {
@@ -43,41 +41,29 @@ pub fn resolve<'doc>(toml: &'doc mut Value, tokens: &Token) -> Result<&'doc mut
                 }
             }
             Value::Array(_) => Err(Error::NoIdentifierInArray(ident.clone())),
-            _ => unimplemented!(),
+            _ => Err(Error::QueryingValueAsTable(ident.clone())),
         },
-        Token::Index { idx, .. } => {
-            match toml {
-                Value::Table(_) => Err(Error::NoIndexInTable(idx)),
-                Value::Array(ref mut ary) => {
-                    if ary.len() > idx {
-                        match tokens.next() {
-                            Some(next) => resolve(ary.get_mut(idx).unwrap(), next),
-                            None => ary.get_mut(idx).ok_or_else(|| unreachable!()),
-                        }
-                    } else if let Some(next) = tokens.next() {
-                        match next {
-                            Token::Identifier { .. } => {
-                                ary.push(Value::Table(Map::new()));
-                            }
-                            Token::Index { .. } => {
-                                ary.push(Value::Array(vec![]));
-                            }
-                        }
-                        //resolve(toml, next)
-                        panic!("Cannot do this")
-                    } else {
-                        unimplemented!()
+        Token::Index { idx, .. } => match toml {
+            Value::Table(_) => Err(Error::NoIndexInTable(idx)),
+            Value::Array(ref mut ary) => {
+                if ary.len() > idx {
+                    match tokens.next() {
+                        Some(next) => resolve(ary.get_mut(idx).unwrap(), next),
+                        None => ary.get_mut(idx).ok_or_else(|| unreachable!()),
                     }
+                } else {
+                    Err(Error::IndexOutOfBounds(idx, ary.len()))
                 }
-                _ => unimplemented!(),
             }
-        }
+            _ => Err(Error::QueryingValueAsArray(idx)),
+        },
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::resolve;
+    use crate::error::Error;
     use crate::tokenizer::*;
     use toml::from_str as toml_from_str;
     use toml::Value;
@@ -512,4 +498,23 @@ mod test {
         //    _                        => panic!("What just happened?"),
         //}
     }
+
+    #[test]
+    fn test_resolve_missing_index_in_array() {
+        let mut toml = toml_from_str("example = [1]").unwrap();
+        let result = do_resolve!(toml => "example.[5].foo");
+
+        assert!(is_match!(result, Err(Error::IndexOutOfBounds(5, 1))));
+    }
+
+    #[test]
+    fn test_resolve_through_value() {
+        let mut toml = toml_from_str("example = 1").unwrap();
+
+        let result = do_resolve!(toml => "example.foo.bar");
+        assert!(is_match!(result, Err(Error::QueryingValueAsTable(_))));
+
+        let result = do_resolve!(toml => "example.[0].bar");
+        assert!(is_match!(result, Err(Error::QueryingValueAsArray(0))));
+    }
 }
@@ -0,0 +1,804 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Declarative validation of documents
+//!
+//! A `Schema` describes the values expected in a document, as a `Rule` per query. `validate()`
+//! checks a document against a schema and returns all violations at once.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::schema::{validate, Rule, Schema};
+//! use toml_query::value::ValueType;
+//!
+//! let schema = Schema::new()
+//!     .with("server.host", Rule::of(ValueType::String).required())
+//!     .unwrap()
+//!     .with("server.port", Rule::of(ValueType::Integer).min(1.0).max(65535.0))
+//!     .unwrap();
+//!
+//! let toml : toml::Value = toml::from_str("[server]\nport = 0").unwrap();
+//! let violations = validate(&toml, &schema);
+//!
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].to_string(), "at server.host: required value is missing");
+//! assert_eq!(violations[1].to_string(), "at server.port: value 0 is out of range 1..=65535");
+//! ```
+
+use std::fmt;
+
+use regex::Regex;
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::query::{Query, Segment};
+use crate::value::ValueType;
+
+/// Describes the values expected in a document
+///
+/// The queries of the rules are relative to the validated value, which is the document itself
+/// for the schema passed to `validate()`.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    rules: Vec<(Query, Rule)>,
+    deny_unknown_keys: bool,
+    other_keys: Option<Box<Rule>>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Add a rule for the value at `query`
+    pub fn with(mut self, query: &str, rule: Rule) -> Result<Self> {
+        self.push(Query::parse(query)?, rule);
+        Ok(self)
+    }
+
+    /// Add a rule for the value at `query`
+    pub fn push(&mut self, query: Query, rule: Rule) {
+        self.rules.push((query, rule))
+    }
+
+    /// Report keys which are not described by the schema
+    ///
+    /// This applies to the validated value itself and all tables on the way to the values which
+    /// have a rule, but not to tables below these values, even if other rules refer to values
+    /// in them. To reject unknown keys there, use a nested schema with `Rule::keys()`.
+    pub fn deny_unknown_keys(mut self) -> Self {
+        self.deny_unknown_keys = true;
+        self
+    }
+
+    /// The rule for keys of the validated value which are not described by the schema
+    ///
+    /// A key is described by the schema if it is the first segment of the query of a rule.
+    pub fn other_keys(mut self, rule: Rule) -> Self {
+        self.other_keys = Some(Box::new(rule));
+        self
+    }
+
+    pub fn rules(&self) -> &[(Query, Rule)] {
+        &self.rules
+    }
+
+    pub fn denies_unknown_keys(&self) -> bool {
+        self.deny_unknown_keys
+    }
+
+    pub fn other_keys_rule(&self) -> Option<&Rule> {
+        self.other_keys.as_deref()
+    }
+
+    /// Returns whether `key` is the first segment of the query of a rule
+    fn describes(&self, key: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|(q, _)| is_match!(q.segments().first(), Some(Segment::Key(k)) if k == key))
+    }
+}
+
+/// Describes a single value
+///
+/// A rule without any constraints accepts every value.
+#[derive(Debug, Clone, Default)]
+pub struct Rule {
+    types: Vec<ValueType>,
+    required: bool,
+    min: Option<f64>,
+    max: Option<f64>,
+    allowed: Vec<Value>,
+    pattern: Option<Regex>,
+    items: Option<Box<Rule>>,
+    keys: Option<Schema>,
+    description: Option<String>,
+    default: Option<Value>,
+    forbidden: bool,
+}
+
+impl Rule {
+    /// A rule which accepts every value
+    pub fn new() -> Self {
+        Rule::default()
+    }
+
+    /// A rule which rejects every value
+    ///
+    /// Combined with `required()`, the value can neither be missing nor present.
+    pub fn never() -> Self {
+        Rule {
+            forbidden: true,
+            ..Rule::default()
+        }
+    }
+
+    /// A rule which accepts values of type `ty`
+    pub fn of(ty: ValueType) -> Self {
+        Rule::any_of(&[ty])
+    }
+
+    /// A rule which accepts values of any of the types in `types`
+    pub fn any_of(types: &[ValueType]) -> Self {
+        Rule {
+            types: types.to_vec(),
+            ..Rule::default()
+        }
+    }
+
+    /// Report a violation if the value is missing
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// The minimum of integer and float values, inclusive
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// The maximum of integer and float values, inclusive
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// The values which are allowed
+    pub fn one_of(mut self, allowed: Vec<Value>) -> Self {
+        self.allowed = allowed;
+        self
+    }
+
+    /// A regular expression which string values have to match
+    pub fn pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| Error::InvalidPattern(String::from(pattern), e.to_string()))?;
+        self.pattern = Some(regex);
+        Ok(self)
+    }
+
+    /// The rule which the elements of array values have to satisfy
+    pub fn items(mut self, rule: Rule) -> Self {
+        self.items = Some(Box::new(rule));
+        self
+    }
+
+    /// The schema which table values have to satisfy, relative to the table
+    pub fn keys(mut self, schema: Schema) -> Self {
+        self.keys = Some(schema);
+        self
+    }
+
+    /// A human readable description of the value, for documentation
+    pub fn with_description<S: Into<String>>(mut self, description: S) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The value which is used if there is no value, for documentation
+    ///
+    /// The default value is not used by `validate()`.
+    pub fn with_default(mut self, default: Value) -> Self {
+        self.default = Some(default);
+        self
+    }
+
+    /// The accepted types, all types are accepted if this is empty
+    pub fn types(&self) -> &[ValueType] {
+        &self.types
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Whether this rule rejects every value, see `Rule::never()`
+    pub fn is_forbidden(&self) -> bool {
+        self.forbidden
+    }
+
+    pub fn range(&self) -> (Option<f64>, Option<f64>) {
+        (self.min, self.max)
+    }
+
+    /// The allowed values, all values are allowed if this is empty
+    pub fn allowed(&self) -> &[Value] {
+        &self.allowed
+    }
+
+    pub fn pattern_str(&self) -> Option<&str> {
+        self.pattern.as_ref().map(Regex::as_str)
+    }
+
+    pub fn item_rule(&self) -> Option<&Rule> {
+        self.items.as_deref()
+    }
+
+    pub fn key_schema(&self) -> Option<&Schema> {
+        self.keys.as_ref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn default_value(&self) -> Option<&Value> {
+        self.default.as_ref()
+    }
+}
+
+/// Types which can describe the values they are deserialized from
+///
+/// Usually this is implemented with `#[derive(Describe)]` for structs with named fields and
+/// enums with unit variants. `#[derive(Partial)]` uses it to generate `Partial::schema()` for
+/// types marked with `#[schema]`.
+pub trait Describe {
+    /// Whether a missing value is accepted, as for `Option`
+    const OPTIONAL: bool = false;
+
+    fn describe() -> Rule;
+}
+
+macro_rules! impl_describe {
+    ($($t:ty),* => $rule:expr) => {
+        $(
+            impl Describe for $t {
+                fn describe() -> Rule {
+                    $rule
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_describe_bounded {
+    ($($t:ty),*) => {
+        $(
+            impl Describe for $t {
+                fn describe() -> Rule {
+                    let rule = Rule::of(ValueType::Integer).min(<$t>::MIN as f64);
+                    if (<$t>::MAX as f64) < (i64::MAX as f64) {
+                        rule.max(<$t>::MAX as f64)
+                    } else {
+                        rule
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_describe!(String, str, char => Rule::of(ValueType::String));
+impl_describe!(bool => Rule::of(ValueType::Boolean));
+impl_describe!(f32, f64 => Rule::any_of(&[ValueType::Integer, ValueType::Float]));
+impl_describe!(i64 => Rule::of(ValueType::Integer));
+impl_describe!(toml::value::Datetime => Rule::of(ValueType::Datetime));
+impl_describe!(Value => Rule::new());
+impl_describe_bounded!(u8, u16, u32, u64, usize, i8, i16, i32, isize);
+
+impl<T: Describe + ?Sized> Describe for &T {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    fn describe() -> Rule {
+        T::describe()
+    }
+}
+
+impl<T: Describe + ?Sized> Describe for Box<T> {
+    const OPTIONAL: bool = T::OPTIONAL;
+
+    fn describe() -> Rule {
+        T::describe()
+    }
+}
+
+impl<T: Describe> Describe for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn describe() -> Rule {
+        T::describe()
+    }
+}
+
+impl<T: Describe> Describe for Vec<T> {
+    fn describe() -> Rule {
+        Rule::of(ValueType::Array).items(T::describe())
+    }
+}
+
+impl<T: Describe> Describe for [T] {
+    fn describe() -> Rule {
+        Rule::of(ValueType::Array).items(T::describe())
+    }
+}
+
+impl<K, T: Describe, S> Describe for std::collections::HashMap<K, T, S> {
+    fn describe() -> Rule {
+        Rule::of(ValueType::Table).keys(Schema::new().other_keys(T::describe()))
+    }
+}
+
+impl<K, T: Describe> Describe for std::collections::BTreeMap<K, T> {
+    fn describe() -> Rule {
+        Rule::of(ValueType::Table).keys(Schema::new().other_keys(T::describe()))
+    }
+}
+
+/// Serialize a default value for `Rule::with_default()`, used by the derive macros
+#[cfg(feature = "typed")]
+#[doc(hidden)]
+pub fn serialize_default<T: serde::Serialize>(value: &T) -> Option<Value> {
+    Value::try_from(value).ok()
+}
+
+/// What is wrong with a value
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViolationKind {
+    /// A required value is missing
+    Missing,
+
+    /// The value has none of the expected types
+    WrongType {
+        expected: Vec<ValueType>,
+        found: ValueType,
+    },
+
+    /// The number is out of the allowed range
+    OutOfRange {
+        value: f64,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+
+    /// The value is not one of the allowed values
+    NotAllowed,
+
+    /// The string does not match the pattern
+    PatternMismatch { pattern: String },
+
+    /// The key is not described by the schema
+    UnknownKey,
+
+    /// There must be no value at all
+    Forbidden,
+}
+
+impl fmt::Display for ViolationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ViolationKind::Missing => write!(f, "required value is missing"),
+            ViolationKind::WrongType { expected, found } => {
+                let expected = expected
+                    .iter()
+                    .map(|t| t.name())
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ViolationKind::OutOfRange { value, min, max } => {
+                let bound = |b: &Option<f64>| b.map(|b| b.to_string()).unwrap_or_default();
+                let upper = match max {
+                    Some(_) => format!("={}", bound(max)),
+                    None => String::new(),
+                };
+                write!(
+                    f,
+                    "value {} is out of range {}..{}",
+                    value,
+                    bound(min),
+                    upper
+                )
+            }
+            ViolationKind::NotAllowed => write!(f, "value is not one of the allowed values"),
+            ViolationKind::PatternMismatch { pattern } => {
+                write!(f, "value does not match pattern '{}'", pattern)
+            }
+            ViolationKind::UnknownKey => write!(f, "unknown key"),
+            ViolationKind::Forbidden => write!(f, "no value is allowed here"),
+        }
+    }
+}
+
+/// A value which does not satisfy its rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    path: Query,
+    kind: ViolationKind,
+}
+
+impl Violation {
+    pub fn new(path: Query, kind: ViolationKind) -> Self {
+        Violation { path, kind }
+    }
+
+    /// The path of the value, relative to the validated document
+    pub fn path(&self) -> &Query {
+        &self.path
+    }
+
+    pub fn kind(&self) -> &ViolationKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.kind)
+        } else {
+            write!(f, "at {}: {}", self.path, self.kind)
+        }
+    }
+}
+
+/// Validate `document` against `schema`
+///
+/// Returns all violations, in the order of the rules in the schema. An empty result means that
+/// the document is valid.
+pub fn validate(document: &Value, schema: &Schema) -> Vec<Violation> {
+    let mut violations = vec![];
+    validate_at(document, &Query::default(), schema, &mut violations);
+    violations
+}
+
+fn validate_at(value: &Value, base: &Query, schema: &Schema, out: &mut Vec<Violation>) {
+    if schema.deny_unknown_keys {
+        unknown_keys(value, base, schema, out);
+    }
+
+    if let (Some(rule), Value::Table(table)) = (schema.other_keys.as_ref(), value) {
+        for (key, v) in table.iter().filter(|(k, _)| !schema.describes(k)) {
+            let mut path = base.clone();
+            path.push(Segment::Key(key.clone()));
+            check(v, &path, rule, out);
+        }
+    }
+
+    for (query, rule) in schema.rules.iter() {
+        let path = base.join(query);
+        match query.get(value) {
+            None if rule.required => out.push(Violation::new(path, ViolationKind::Missing)),
+            None => {}
+            Some(v) => check(v, &path, rule, out),
+        }
+    }
+}
+
+/// Report the keys of tables on the way to the rules of `schema` which are not on such a way
+fn unknown_keys(value: &Value, base: &Query, schema: &Schema, out: &mut Vec<Violation>) {
+    let mut known: Vec<(Query, Vec<&Segment>)> = vec![];
+    for (query, _) in schema.rules.iter() {
+        for (n, segment) in query.segments().iter().enumerate() {
+            let prefix = query.prefix(n);
+            match known.iter_mut().find(|(q, _)| *q == prefix) {
+                Some((_, segments)) => segments.push(segment),
+                None => known.push((prefix, vec![segment])),
+            }
+        }
+    }
+
+    if known.is_empty() {
+        known.push((Query::default(), vec![]));
+    }
+
+    for (prefix, segments) in known {
+        // the keys of a value with a rule of its own are described by that rule
+        if schema.rules.iter().any(|(q, _)| *q == prefix) {
+            continue;
+        }
+
+        if let Some(Value::Table(table)) = prefix.get(value) {
+            for key in table.keys() {
+                let key = Segment::Key(key.clone());
+                if !segments.contains(&&key) {
+                    let mut path = base.join(&prefix);
+                    path.push(key);
+                    out.push(Violation::new(path, ViolationKind::UnknownKey));
+                }
+            }
+        }
+    }
+}
+
+fn check(value: &Value, path: &Query, rule: &Rule, out: &mut Vec<Violation>) {
+    let mut violate = |kind| out.push(Violation::new(path.clone(), kind));
+
+    if rule.forbidden {
+        violate(ViolationKind::Forbidden);
+        return;
+    }
+
+    let found = ValueType::of(value);
+    if !rule.types.is_empty() && !rule.types.contains(&found) {
+        let expected = rule.types.clone();
+        violate(ViolationKind::WrongType { expected, found });
+        return;
+    }
+
+    let number = match value {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    };
+    if let Some(number) = number {
+        let too_small = rule.min.map(|min| number < min).unwrap_or(false);
+        let too_large = rule.max.map(|max| number > max).unwrap_or(false);
+        if too_small || too_large {
+            violate(ViolationKind::OutOfRange {
+                value: number,
+                min: rule.min,
+                max: rule.max,
+            });
+        }
+    }
+
+    if !rule.allowed.is_empty() && !rule.allowed.contains(value) {
+        violate(ViolationKind::NotAllowed);
+    }
+
+    if let (Some(pattern), Value::String(s)) = (rule.pattern.as_ref(), value) {
+        if !pattern.is_match(s) {
+            let pattern = String::from(pattern.as_str());
+            violate(ViolationKind::PatternMismatch { pattern });
+        }
+    }
+
+    match (value, rule.items.as_ref(), rule.keys.as_ref()) {
+        (Value::Array(items), Some(item_rule), _) => {
+            for (i, item) in items.iter().enumerate() {
+                let mut item_path = path.clone();
+                item_path.push(Segment::Index(i));
+                check(item, &item_path, item_rule, out);
+            }
+        }
+        (Value::Table(_), _, Some(schema)) => validate_at(value, path, schema, out),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    fn strings(values: &[&str]) -> Vec<Value> {
+        values
+            .iter()
+            .map(|s| Value::String(String::from(*s)))
+            .collect()
+    }
+
+    fn messages(violations: &[Violation]) -> Vec<String> {
+        violations.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let toml: Value = toml_from_str("name = \"my-app\"\n[server]\nport = 80").unwrap();
+        let schema = Schema::new()
+            .with("name", Rule::of(ValueType::String).required())
+            .unwrap()
+            .with("server.port", Rule::of(ValueType::Integer).min(1.0))
+            .unwrap()
+            .with("timeout", Rule::of(ValueType::Integer))
+            .unwrap();
+
+        assert!(validate(&toml, &schema).is_empty());
+    }
+
+    #[test]
+    fn test_validate_all_violations() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "my-app"
+        level = "verbose"
+        ratio = 1.5
+
+        [server]
+        host = "localhost"
+        port = 80
+
+        [[users]]
+        name = "alice"
+        admin = true
+
+        [[users]]
+        name = "Bob"
+        admin = "no"
+        "#,
+        )
+        .unwrap();
+
+        let user = Schema::new()
+            .with(
+                "name",
+                Rule::of(ValueType::String).pattern("^[a-z]+$").unwrap(),
+            )
+            .unwrap()
+            .with("admin", Rule::of(ValueType::Boolean))
+            .unwrap();
+
+        let schema = Schema::new()
+            .with("name", Rule::of(ValueType::Integer))
+            .unwrap()
+            .with(
+                "level",
+                Rule::of(ValueType::String).one_of(strings(&["debug", "info"])),
+            )
+            .unwrap()
+            .with(
+                "ratio",
+                Rule::any_of(&[ValueType::Integer, ValueType::Float]).max(1.0),
+            )
+            .unwrap()
+            .with("server.port", Rule::new().min(1024.0).max(65535.0))
+            .unwrap()
+            .with("server.tls.cert", Rule::of(ValueType::String).required())
+            .unwrap()
+            .with(
+                "users",
+                Rule::of(ValueType::Array).items(Rule::new().keys(user)),
+            )
+            .unwrap();
+
+        assert_eq!(
+            messages(&validate(&toml, &schema)),
+            vec![
+                "at name: expected Integer, found String",
+                "at level: value is not one of the allowed values",
+                "at ratio: value 1.5 is out of range ..=1",
+                "at server.port: value 80 is out of range 1024..=65535",
+                "at server.tls.cert: required value is missing",
+                "at users.[1].name: value does not match pattern '^[a-z]+$'",
+                "at users.[1].admin: expected Boolean, found String",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_unknown_keys() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "my-app"
+        level = "verbose"
+        ratio = 1.5
+
+        [server]
+        host = "localhost"
+        port = 80
+
+        [[users]]
+        name = "alice"
+        admin = true
+
+        [[users]]
+        name = "Bob"
+        admin = "no"
+        "#,
+        )
+        .unwrap();
+
+        let schema = Schema::new()
+            .with("name", Rule::new())
+            .unwrap()
+            .with("server.port", Rule::new())
+            .unwrap()
+            .deny_unknown_keys();
+
+        let violations = validate(&toml, &schema);
+        assert_eq!(
+            messages(&violations),
+            vec![
+                "at level: unknown key",
+                "at ratio: unknown key",
+                "at users: unknown key",
+                "at server.host: unknown key",
+            ]
+        );
+        assert_eq!(violations[0].kind(), &ViolationKind::UnknownKey);
+        assert_eq!(violations[3].path(), &Query::parse("server.host").unwrap());
+
+        let empty = Schema::new().deny_unknown_keys();
+        assert_eq!(validate(&toml, &empty).len(), 5);
+
+        let nested = Schema::new()
+            .with("server", Rule::of(ValueType::Table))
+            .unwrap()
+            .with("server.port", Rule::of(ValueType::Integer))
+            .unwrap()
+            .with("name", Rule::new())
+            .unwrap()
+            .deny_unknown_keys();
+        assert_eq!(
+            messages(&validate(&toml, &nested)),
+            vec![
+                "at level: unknown key",
+                "at ratio: unknown key",
+                "at users: unknown key",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_other_keys() {
+        let toml: Value = toml_from_str(
+            r#"
+        name = "my-app"
+        level = "verbose"
+        ratio = 1.5
+        users = []
+
+        [server]
+        port = 80
+        "#,
+        )
+        .unwrap();
+
+        let schema = Schema::new()
+            .with("name", Rule::new())
+            .unwrap()
+            .with("server.port", Rule::new())
+            .unwrap()
+            .other_keys(Rule::of(ValueType::String));
+
+        assert_eq!(
+            messages(&validate(&toml, &schema)),
+            vec![
+                "at ratio: expected String, found Float",
+                "at users: expected String, found Array",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_never() {
+        let toml: Value = toml_from_str("name = \"my-app\"").unwrap();
+        let schema = Schema::new()
+            .with("name", Rule::never())
+            .unwrap()
+            .with("nonexistent", Rule::never())
+            .unwrap()
+            .with("missing", Rule::never().required())
+            .unwrap();
+
+        assert_eq!(
+            messages(&validate(&toml, &schema)),
+            vec![
+                "at name: no value is allowed here",
+                "at missing: required value is missing",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let err = Rule::new().pattern("(").unwrap_err();
+        assert!(is_match!(err, Error::InvalidPattern(..)));
+    }
+}
@@ -4,11 +4,16 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 //
 
+#[cfg(feature = "typed")]
+use std::borrow::Borrow;
+
 #[cfg(feature = "typed")]
 use serde::Serialize;
 use toml::Value;
 
 use crate::error::{Error, Result};
+#[cfg(feature = "typed")]
+use crate::read::{partial_writes, Partial};
 use crate::tokenizer::tokenize_with_seperator;
 use crate::tokenizer::Token;
 
@@ -48,6 +53,31 @@ pub trait TomlValueSetExt {
         let value = Value::try_from(value).map_err(Error::TomlSerialize)?;
         self.set(query, value)
     }
+
+    /// Serialize a `Partial` and set it at its location
+    ///
+    /// The value at `P::LOCATION` is replaced. Fields with a `#[location]` of their own are set at
+    /// the first of their locations, fallback locations are never written. As with `set()`, the
+    /// parents of these locations have to exist. If one of them does not, an error is returned
+    /// and nothing is written.
+    #[cfg(feature = "typed")]
+    fn write_partial<'a, P: Partial<'a>>(&mut self, partial: &P::Output) -> Result<()>
+    where
+        Self: Borrow<Value>,
+    {
+        let writes = partial_writes::<P>(partial)?;
+
+        // Try all writes on a copy first, so a failing one cannot leave a half-written document
+        let mut probe: Value = (*self).borrow().clone();
+        for (location, value) in writes.iter() {
+            probe.set(location, value.clone())?;
+        }
+
+        for (location, value) in writes {
+            self.set(location, value)?;
+        }
+        Ok(())
+    }
 }
 
 impl TomlValueSetExt for Value {
@@ -60,10 +90,10 @@ impl TomlValueSetExt for Value {
         use crate::resolver::mut_resolver::resolve;
 
         let mut tokens = tokenize_with_seperator(query, sep)?;
-        let last = tokens.pop_last();
-
-        let val = resolve(self, &tokens, true)?.unwrap(); // safe because of resolve() guarantees
-        let last = last.unwrap_or_else(|| Box::new(tokens));
+        let (val, last) = match tokens.pop_last() {
+            None => (self, Box::new(tokens)),
+            Some(last) => (resolve(self, &tokens, true)?.unwrap(), last), // safe because of resolve() guarantees
+        };
 
         match *last {
             Token::Identifier { ident, .. } => match val {
@@ -75,9 +105,7 @@ impl TomlValueSetExt for Value {
             Token::Index { idx, .. } => match val {
                 Value::Array(ref mut a) => {
                     if a.len() > idx {
-                        let result = a.swap_remove(idx);
-                        a.insert(idx, value);
-                        Ok(Some(result))
+                        Ok(Some(::std::mem::replace(&mut a[idx], value)))
                     } else {
                         a.push(value);
                         Ok(None)
@@ -227,6 +255,38 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_set_with_seperator_into_array_keeps_order() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        array = [ 0, 1, 2 ]
+        "#,
+        )
+        .unwrap();
+
+        let res = toml.set_with_seperator(&String::from("array.[0]"), '.', Value::Integer(3));
+
+        assert!(res.is_ok());
+        assert!(is_match!(res.unwrap(), Some(Value::Integer(0))));
+
+        let expected: Value = toml_from_str("array = [ 3, 1, 2 ]").unwrap();
+        assert_eq!(toml, expected);
+    }
+
+    #[test]
+    fn test_set_one_token() {
+        let mut toml: Value = toml_from_str("a = 0").unwrap();
+
+        let res = toml.set(&String::from("a"), Value::Integer(1));
+        assert!(is_match!(res.unwrap(), Some(Value::Integer(0))));
+
+        let res = toml.set(&String::from("b"), Value::Integer(2));
+        assert!(res.unwrap().is_none());
+
+        let expected: Value = toml_from_str("a = 1\nb = 2").unwrap();
+        assert_eq!(toml, expected);
+    }
+
     #[test]
     fn test_set_with_seperator_into_table_index_nonexistent() {
         use std::ops::Index;
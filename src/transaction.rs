@@ -0,0 +1,213 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Transactions over multiple mutations of a document
+//!
+//! A `Transaction` borrows a document mutably and implements the set, insert and delete
+//! extension traits. Each mutation done through the transaction records how to undo it. If the
+//! transaction is dropped without calling `Transaction::commit()`, all mutations are rolled back.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::insert::TomlValueInsertExt;
+//! use toml_query::read::TomlValueReadExt;
+//! use toml_query::set::TomlValueSetExt;
+//! use toml_query::transaction::Transaction;
+//!
+//! let mut toml : toml::Value = toml::from_str("[table]\na = 1").unwrap();
+//!
+//! {
+//!     let mut tx = Transaction::new(&mut toml);
+//!     tx.set("table.a", toml::Value::Integer(2)).unwrap();
+//!     assert!(tx.insert("table.[0]", toml::Value::Integer(3)).is_err());
+//!     // tx is dropped here and rolled back
+//! }
+//!
+//! assert_eq!(toml.read("table.a").unwrap(), Some(&toml::Value::Integer(1)));
+//! ```
+
+use toml::Value;
+
+use crate::delete::TomlValueDeleteExt;
+use crate::error::Result;
+use crate::insert::TomlValueInsertExt;
+use crate::set::TomlValueSetExt;
+use crate::undo::Undo;
+
+/// A transaction over a mutably borrowed document
+///
+/// All mutations done via the `TomlValueSetExt`, `TomlValueInsertExt` and `TomlValueDeleteExt`
+/// implementations of this type are rolled back when the transaction is dropped, unless
+/// `Transaction::commit()` was called.
+///
+/// Reading the transaction as `toml::Value` shows the document with the mutations done so far.
+#[derive(Debug)]
+pub struct Transaction<'doc> {
+    document: &'doc mut Value,
+    undo: Vec<Undo>,
+}
+
+impl<'doc> Transaction<'doc> {
+    pub fn new(document: &'doc mut Value) -> Self {
+        Transaction {
+            document,
+            undo: vec![],
+        }
+    }
+
+    /// Keep all mutations done in this transaction
+    pub fn commit(mut self) {
+        self.undo.clear();
+    }
+
+    /// Undo all mutations done in this transaction
+    ///
+    /// This is what happens if the transaction is dropped, but errors are reported here.
+    pub fn rollback(mut self) -> Result<()> {
+        self.rollback_in_place()
+    }
+
+    fn rollback_in_place(&mut self) -> Result<()> {
+        while let Some(undo) = self.undo.pop() {
+            undo.apply(self.document)?;
+        }
+        Ok(())
+    }
+}
+
+crate::util::impl_document_wrapper!(Transaction<'doc>);
+
+impl<'doc> Drop for Transaction<'doc> {
+    fn drop(&mut self) {
+        if let Err(_e) = self.rollback_in_place() {
+            error!("Rolling back transaction failed: {}", _e);
+        }
+    }
+}
+
+impl<'doc> TomlValueSetExt for Transaction<'doc> {
+    fn set_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        let (old, undo) = crate::undo::set(self.document, query, sep, value)?;
+        self.undo.push(undo);
+        Ok(old)
+    }
+}
+
+impl<'doc> TomlValueInsertExt for Transaction<'doc> {
+    fn insert_with_seperator(
+        &mut self,
+        query: &str,
+        sep: char,
+        value: Value,
+    ) -> Result<Option<Value>> {
+        let (old, undo) = crate::undo::insert(self.document, query, sep, value)?;
+        self.undo.push(undo);
+        Ok(old)
+    }
+}
+
+impl<'doc> TomlValueDeleteExt for Transaction<'doc> {
+    fn delete_with_seperator(&mut self, query: &str, sep: char) -> Result<Option<Value>> {
+        let (removed, undo) = crate::undo::delete(self.document, query, sep)?;
+        self.undo.extend(undo);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::read::TomlValueReadExt;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_commit_keeps_changes() {
+        let mut toml: Value = toml_from_str("[table]\na = 1\nempty = []").unwrap();
+        {
+            let mut tx = Transaction::new(&mut toml);
+            assert!(tx.set("table.a", Value::Integer(2)).is_ok());
+            assert!(tx.insert("other.b", Value::Integer(3)).is_ok());
+            assert!(tx.delete("table.empty").is_ok());
+            tx.commit();
+        }
+
+        assert_eq!(toml.read("table.a").unwrap(), Some(&Value::Integer(2)));
+        assert_eq!(toml.read("other.b").unwrap(), Some(&Value::Integer(3)));
+        assert!(toml.read("table.empty").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_drop_rolls_back() {
+        let mut toml: Value = toml_from_str("[table]\na = 1\nempty = []").unwrap();
+        let original = toml.clone();
+        {
+            let mut tx = Transaction::new(&mut toml);
+            assert!(tx.set("table.a", Value::Integer(2)).is_ok());
+            assert!(tx.set("table.b", Value::Integer(2)).is_ok());
+            assert!(tx.insert("other.deeply.nested", Value::Integer(3)).is_ok());
+            assert!(tx.delete("table.empty").is_ok());
+            assert_eq!(tx.read("table.a").unwrap(), Some(&Value::Integer(2)));
+        }
+
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_rollback_arrays() {
+        let mut toml: Value = toml_from_str(
+            r#"
+        [table]
+        a = 1
+        array = [1, 2, 3]
+        empty = []
+        "#,
+        )
+        .unwrap();
+        let original = toml.clone();
+        {
+            let mut tx = Transaction::new(&mut toml);
+            assert!(tx.set("table.array.[0]", Value::Integer(10)).is_ok());
+            assert!(tx.set("table.array.[10]", Value::Integer(4)).is_ok());
+            assert!(tx.insert("table.array.[1]", Value::Integer(5)).is_ok());
+            assert!(tx.insert("table.array.[100]", Value::Integer(6)).is_ok());
+            assert!(tx.set("table.empty.[0]", Value::Integer(7)).is_ok());
+            assert!(tx.delete("table.empty.[0]").is_ok());
+            assert!(tx.delete("table.array.[2]").is_ok());
+        }
+
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_rollback_failed_insert() {
+        let mut toml: Value = toml_from_str("[table]\na = 1").unwrap();
+        let original = toml.clone();
+        {
+            let mut tx = Transaction::new(&mut toml);
+            assert!(tx.insert("other.[0]", Value::Integer(1)).is_err());
+        }
+
+        assert_eq!(toml, original);
+    }
+
+    #[test]
+    fn test_explicit_rollback() {
+        let mut toml: Value = toml_from_str("[table]\na = 1").unwrap();
+        let original = toml.clone();
+        let mut tx = Transaction::new(&mut toml);
+        assert!(tx.set("table.a", Value::Integer(2)).is_ok());
+        assert!(tx.rollback().is_ok());
+
+        assert_eq!(toml, original);
+    }
+}
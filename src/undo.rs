@@ -0,0 +1,187 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Recording the inverse of mutations
+//!
+//! The functions in this module perform a set, insert or delete operation on a document and
+//! return, alongside the result of the operation, the `Undo` which reverts it.
+
+use toml::Value;
+
+use crate::delete::TomlValueDeleteExt;
+use crate::error::{Error, Result};
+use crate::insert::TomlValueInsertExt;
+use crate::query::{Query, Segment};
+use crate::set::TomlValueSetExt;
+
+/// The inverse of a single mutation of a document
+#[derive(Debug, Clone)]
+pub enum Undo {
+    /// Put the value back at the location, replacing what is there
+    Restore(Query, Value),
+
+    /// Insert the value at the location, shifting array elements
+    Reinsert(Query, Value),
+
+    /// Remove what is at the location
+    Remove(Query),
+}
+
+impl Undo {
+    /// Compute the inverse of writing to `query` in `document`, before the write happens
+    ///
+    /// `shifting` tells whether the write inserts into arrays (as `insert` does) rather than
+    /// replacing the element at the index (as `set` does).
+    pub fn for_write(document: &Value, query: &Query, shifting: bool) -> Undo {
+        let len = query.segments().len();
+        let first_missing = (1..=len).find(|n| query.prefix(*n).get(document).is_none());
+
+        match first_missing {
+            None => match query.last() {
+                Some(Segment::Index(_)) if shifting => Undo::Remove(query.clone()),
+                _ => Undo::Restore(query.clone(), query.get(document).cloned().unwrap()), // safe because present
+            },
+
+            Some(n) => {
+                let created = query.prefix(n);
+                match created.last() {
+                    // Writing out of bounds of an array appends to the array
+                    Some(Segment::Index(_)) => {
+                        let mut parent = created.parent().unwrap(); // safe because n >= 1
+                        let len = parent
+                            .get(document)
+                            .and_then(Value::as_array)
+                            .map(Vec::len)
+                            .unwrap_or(0);
+                        parent.push(Segment::Index(len));
+                        Undo::Remove(parent)
+                    }
+                    _ => Undo::Remove(created),
+                }
+            }
+        }
+    }
+
+    /// Compute the inverse of deleting `removed` from `query`
+    pub fn for_delete(query: Query, removed: Value) -> Undo {
+        match query.last() {
+            Some(Segment::Index(_)) => Undo::Reinsert(query, removed),
+            _ => Undo::Restore(query, removed),
+        }
+    }
+
+    /// Apply the inverse operation to `document`
+    pub fn apply(self, document: &mut Value) -> Result<()> {
+        let (query, value) = match self {
+            Undo::Restore(ref q, ref v) | Undo::Reinsert(ref q, ref v) => (q, Some(v)),
+            Undo::Remove(ref q) => (q, None),
+        };
+
+        let parent_query = query.parent().unwrap_or_default();
+        let parent = parent_query
+            .get_mut(document)
+            .ok_or_else(|| Error::NotAvailable(parent_query.to_string()))?;
+
+        match (query.last(), parent) {
+            (None, doc) => {
+                if let Some(v) = value {
+                    *doc = v.clone();
+                }
+            }
+
+            (Some(Segment::Key(key)), Value::Table(ref mut t)) => match value {
+                Some(v) => {
+                    t.insert(key.clone(), v.clone());
+                }
+                None => {
+                    t.remove(key);
+                }
+            },
+
+            (Some(Segment::Index(idx)), Value::Array(ref mut a)) => {
+                let idx = *idx;
+                match self {
+                    Undo::Restore(_, v) if idx < a.len() => a[idx] = v,
+                    Undo::Restore(_, v) | Undo::Reinsert(_, v) => a.insert(idx.min(a.len()), v),
+                    Undo::Remove(_) if idx < a.len() => {
+                        a.remove(idx);
+                    }
+                    Undo::Remove(_) => {}
+                }
+            }
+
+            (Some(Segment::Key(_)), other) => {
+                let act = crate::util::name_of_val(other);
+                return Err(Error::CannotAccessBecauseTypeMismatch("Table", act));
+            }
+
+            (Some(Segment::Index(_)), other) => {
+                let act = crate::util::name_of_val(other);
+                return Err(Error::CannotAccessBecauseTypeMismatch("Array", act));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Set a value in `document` and record how to undo it
+///
+/// See documentation of `TomlValueSetExt::set_with_seperator`
+pub fn set(
+    document: &mut Value,
+    query: &str,
+    sep: char,
+    value: Value,
+) -> Result<(Option<Value>, Undo)> {
+    let q = Query::parse_with_seperator(query, sep)?;
+    let undo = Undo::for_write(document, &q, false);
+    let old = document.set_with_seperator(query, sep, value)?;
+    Ok((old, undo))
+}
+
+/// Insert a value in `document` and record how to undo it
+///
+/// Other than `TomlValueInsertExt::insert_with_seperator`, this leaves the document untouched if
+/// the insert fails after creating intermediate tables.
+pub fn insert(
+    document: &mut Value,
+    query: &str,
+    sep: char,
+    value: Value,
+) -> Result<(Option<Value>, Undo)> {
+    let q = Query::parse_with_seperator(query, sep)?;
+    let existed = q.get(document).is_some();
+    let undo = Undo::for_write(document, &q, true);
+
+    match document.insert_with_seperator(query, sep, value) {
+        Ok(old) => Ok((old, undo)),
+        Err(e) => {
+            if let Undo::Remove(ref created) = undo {
+                if !existed && created.get(document).is_some() {
+                    undo.apply(document)?;
+                }
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Delete a value from `document` and record how to undo it
+///
+/// If nothing was deleted, there is nothing to undo.
+pub fn delete(
+    document: &mut Value,
+    query: &str,
+    sep: char,
+) -> Result<(Option<Value>, Option<Undo>)> {
+    let q = Query::parse_with_seperator(query, sep)?;
+    let removed = document.delete_with_seperator(query, sep)?;
+    let undo = removed
+        .as_ref()
+        .map(|removed| Undo::for_delete(q, removed.clone()));
+    Ok((removed, undo))
+}
@@ -0,0 +1,218 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Getters for durations and byte sizes
+//!
+//! Durations are read either from integers, which are interpreted as milliseconds, or from
+//! strings like `"30s"`, `"5m"` or `"1h 30m"`. The units `ns`, `us`, `ms`, `s`, `m`, `h` and `d`
+//! are supported.
+//!
+//! Byte sizes are read either from integers, which are interpreted as bytes, or from strings like
+//! `"512MiB"` or `"1 GB"`. Decimal (`kB`, `MB`, `GB`, `TB`, `PB`) and binary (`KiB`, `MiB`,
+//! `GiB`, `TiB`, `PiB`) units are supported, units are matched case-insensitively.
+
+use std::time::Duration;
+
+use toml::Value;
+
+use crate::error::{Error, Result};
+use crate::read::TomlValueReadExt;
+
+/// Extension for reading durations and byte sizes
+pub trait TomlValueReadUnitExt<'doc>: TomlValueReadExt<'doc> {
+    fn read_duration(&'doc self, query: &str) -> Result<Option<Duration>>;
+    fn read_byte_size(&'doc self, query: &str) -> Result<Option<u64>>;
+}
+
+impl<'doc, T> TomlValueReadUnitExt<'doc> for T
+where
+    T: TomlValueReadExt<'doc>,
+{
+    fn read_duration(&'doc self, query: &str) -> Result<Option<Duration>> {
+        match self.read(query)? {
+            None => Ok(None),
+            Some(&Value::Integer(ms)) if ms >= 0 => Ok(Some(Duration::from_millis(ms as u64))),
+            Some(&Value::Integer(ms)) => Err(negative(query, ms)),
+            Some(Value::String(ref s)) => parse_duration(s)
+                .map(Some)
+                .ok_or_else(|| Error::UnitParseError(String::from(query), s.clone(), "Duration")),
+            Some(other) => Err(Error::TypeError(
+                "Duration",
+                crate::util::name_of_val(other),
+            )),
+        }
+    }
+
+    fn read_byte_size(&'doc self, query: &str) -> Result<Option<u64>> {
+        match self.read(query)? {
+            None => Ok(None),
+            Some(&Value::Integer(bytes)) if bytes >= 0 => Ok(Some(bytes as u64)),
+            Some(&Value::Integer(bytes)) => Err(negative(query, bytes)),
+            Some(Value::String(ref s)) => parse_byte_size(s)
+                .map(Some)
+                .ok_or_else(|| Error::UnitParseError(String::from(query), s.clone(), "ByteSize")),
+            Some(other) => Err(Error::TypeError(
+                "ByteSize",
+                crate::util::name_of_val(other),
+            )),
+        }
+    }
+}
+
+fn negative(query: &str, i: i64) -> Error {
+    Error::OutOfRange(
+        String::from(query),
+        i.to_string(),
+        String::from("0"),
+        i64::MAX.to_string(),
+    )
+}
+
+/// Split a string like `"1h 30m"` into `[(1, "h"), (30, "m")]`
+///
+/// A number without unit yields an empty unit. Returns `None` if the string is empty or a unit
+/// is not preceded by a number.
+fn split_quantities(s: &str) -> Option<Vec<(u64, &str)>> {
+    let mut rest = s.trim();
+    let mut quantities = vec![];
+
+    while !rest.is_empty() {
+        let num_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let num = rest[..num_end].parse().ok()?;
+        rest = rest[num_end..].trim_start();
+
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c.is_whitespace())
+            .unwrap_or(rest.len());
+        quantities.push((num, &rest[..unit_end]));
+        rest = rest[unit_end..].trim_start();
+    }
+
+    if quantities.is_empty() {
+        None
+    } else {
+        Some(quantities)
+    }
+}
+
+fn parse_duration(s: &str) -> Option<Duration> {
+    split_quantities(s)?
+        .into_iter()
+        .try_fold(Duration::from_secs(0), |acc, (num, unit)| {
+            let part = match unit {
+                "ns" => Duration::from_nanos(num),
+                "us" => Duration::from_micros(num),
+                "ms" => Duration::from_millis(num),
+                "s" => Duration::from_secs(num),
+                "m" => Duration::from_secs(num.checked_mul(60)?),
+                "h" => Duration::from_secs(num.checked_mul(60 * 60)?),
+                "d" => Duration::from_secs(num.checked_mul(24 * 60 * 60)?),
+                _ => return None,
+            };
+            acc.checked_add(part)
+        })
+}
+
+fn parse_byte_size(s: &str) -> Option<u64> {
+    match split_quantities(s)?.as_slice() {
+        [(num, unit)] => {
+            let factor: u64 = match unit.to_ascii_lowercase().as_str() {
+                "" | "b" => 1,
+                "kb" => 1000,
+                "mb" => 1000u64.pow(2),
+                "gb" => 1000u64.pow(3),
+                "tb" => 1000u64.pow(4),
+                "pb" => 1000u64.pow(5),
+                "kib" => 1024,
+                "mib" => 1024u64.pow(2),
+                "gib" => 1024u64.pow(3),
+                "tib" => 1024u64.pow(4),
+                "pib" => 1024u64.pow(5),
+                _ => return None,
+            };
+            num.checked_mul(factor)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration(" 1h 30m "), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86400)));
+
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("30"), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("30 seconds"), None);
+        assert_eq!(parse_duration("-5s"), None);
+    }
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("512MiB"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_byte_size("1GB"), Some(1_000_000_000));
+        assert_eq!(parse_byte_size("1 kib"), Some(1024));
+        assert_eq!(parse_byte_size("100"), Some(100));
+        assert_eq!(parse_byte_size("100B"), Some(100));
+
+        assert_eq!(parse_byte_size(""), None);
+        assert_eq!(parse_byte_size("1GB 1MB"), None);
+        assert_eq!(parse_byte_size("1XB"), None);
+        assert_eq!(parse_byte_size("100000000PiB"), None);
+    }
+
+    #[test]
+    fn test_read_duration_and_byte_size() {
+        let toml: Value = toml_from_str(
+            r#"
+        timeout = "1h30m"
+        interval = 1500
+        cache = "512MiB"
+        buffer = 4096
+        invalid = "five minutes"
+        flag = true
+        negative = -1
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            toml.read_duration("timeout").unwrap(),
+            Some(Duration::from_secs(5400))
+        );
+        assert_eq!(
+            toml.read_duration("interval").unwrap(),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(
+            toml.read_byte_size("cache").unwrap(),
+            Some(512 * 1024 * 1024)
+        );
+        assert_eq!(toml.read_byte_size("buffer").unwrap(), Some(4096));
+        assert!(toml.read_duration("nonexistent").unwrap().is_none());
+
+        let err = toml.read_duration("invalid").unwrap_err();
+        assert!(is_match!(err, Error::UnitParseError(..)));
+
+        let err = toml.read_duration("negative").unwrap_err();
+        assert!(is_match!(err, Error::OutOfRange(..)));
+
+        let err = toml.read_byte_size("flag").unwrap_err();
+        assert!(is_match!(err, Error::TypeError("ByteSize", "Boolean")));
+    }
+}
@@ -6,14 +6,71 @@
 
 use toml::Value;
 
+use crate::error::{Error, Result};
+use crate::tokenizer::tokenize_with_seperator;
+use crate::tokenizer::Token;
+use crate::value::ValueType;
+
+/// Let a wrapper type, which keeps its document in a `document` field, be read as `toml::Value`
+macro_rules! impl_document_wrapper {
+    ($ty:ident $(<$lt:lifetime>)?) => {
+        impl$(<$lt>)? ::std::ops::Deref for $ty$(<$lt>)? {
+            type Target = ::toml::Value;
+
+            fn deref(&self) -> &::toml::Value {
+                ::std::borrow::Borrow::<::toml::Value>::borrow(&self.document)
+            }
+        }
+
+        impl$(<$lt>)? ::std::borrow::Borrow<::toml::Value> for $ty$(<$lt>)? {
+            fn borrow(&self) -> &::toml::Value {
+                ::std::borrow::Borrow::<::toml::Value>::borrow(&self.document)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_document_wrapper;
+
 pub fn name_of_val(val: &Value) -> &'static str {
-    match *val {
-        Value::Array(_) => "Array",
-        Value::Boolean(_) => "Boolean",
-        Value::Datetime(_) => "Datetime",
-        Value::Float(_) => "Float",
-        Value::Integer(_) => "Integer",
-        Value::String(_) => "String",
-        Value::Table(_) => "Table",
+    ValueType::of(val).name()
+}
+
+/// Remove the value at `query` from `doc`
+///
+/// Other than `TomlValueDeleteExt::delete_with_seperator()`, this function also removes
+/// non-empty tables and arrays, that is, it always removes the whole subtree.
+///
+/// Returns `Ok(None)` if there was nothing to remove.
+pub fn remove_with_seperator(doc: &mut Value, query: &str, sep: char) -> Result<Option<Value>> {
+    use crate::resolver::mut_resolver::resolve;
+
+    let mut tokens = tokenize_with_seperator(query, sep)?;
+    let (val, last) = match tokens.pop_last() {
+        None => (doc, Box::new(tokens)),
+        Some(last) => match resolve(doc, &tokens, false)? {
+            Some(val) => (val, last),
+            None => return Ok(None),
+        },
+    };
+
+    match *last {
+        Token::Identifier { ident, .. } => match val {
+            Value::Table(ref mut t) => Ok(t.remove(&ident)),
+            Value::Array(_) => Err(Error::NoIdentifierInArray(ident)),
+            _ => Err(Error::QueryingValueAsTable(ident)),
+        },
+
+        Token::Index { idx, .. } => match val {
+            Value::Array(ref mut a) => {
+                if a.len() > idx {
+                    Ok(Some(a.remove(idx)))
+                } else {
+                    Ok(None)
+                }
+            }
+            Value::Table(_) => Err(Error::NoIndexInTable(idx)),
+            _ => Err(Error::QueryingValueAsArray(idx)),
+        },
     }
 }
@@ -119,3 +119,49 @@ pub trait TomlValueExt<'doc>:
 }
 
 impl<'doc> TomlValueExt<'doc> for Value {}
+
+/// The type of a `toml::Value`, without the value itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    String,
+    Integer,
+    Float,
+    Boolean,
+    Datetime,
+    Array,
+    Table,
+}
+
+impl ValueType {
+    /// The type of `value`
+    pub fn of(value: &Value) -> ValueType {
+        match value {
+            Value::String(_) => ValueType::String,
+            Value::Integer(_) => ValueType::Integer,
+            Value::Float(_) => ValueType::Float,
+            Value::Boolean(_) => ValueType::Boolean,
+            Value::Datetime(_) => ValueType::Datetime,
+            Value::Array(_) => ValueType::Array,
+            Value::Table(_) => ValueType::Table,
+        }
+    }
+
+    /// The name of the type, as used in `Error::TypeError`
+    pub fn name(self) -> &'static str {
+        match self {
+            ValueType::String => "String",
+            ValueType::Integer => "Integer",
+            ValueType::Float => "Float",
+            ValueType::Boolean => "Boolean",
+            ValueType::Datetime => "Datetime",
+            ValueType::Array => "Array",
+            ValueType::Table => "Table",
+        }
+    }
+}
+
+impl ::std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,187 @@
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+//
+
+//! Enumerating the values of a document
+//!
+//! The extension traits go from a query to a value. `paths()` and `leaves()` go the other way:
+//! they yield every value of a document together with the query which refers to it. The keys of
+//! a table are visited in the iteration order of `toml::map::Map`: in the order of the document
+//! text if the `preserve_order` feature of `toml` is enabled, sorted otherwise.
+//!
+//! ```rust
+//! extern crate toml;
+//! extern crate toml_query;
+//!
+//! use toml_query::walk::leaves;
+//!
+//! let toml : toml::Value = toml::from_str("a = 1\n[b]\nc = [true]").unwrap();
+//! let queries = leaves(&toml).map(|(q, _)| q.to_string()).collect::<Vec<_>>();
+//! assert_eq!(queries, vec!["a", "b.c.[0]"]);
+//! ```
+
+use toml::Value;
+
+use crate::query::{Query, Segment};
+
+/// Iterator over the values of a document, see `paths()` and `leaves()`
+#[derive(Debug, Clone)]
+pub struct Paths<'doc> {
+    /// The values which are still to be visited, the next one last
+    stack: Vec<(Query, &'doc Value)>,
+    leaves_only: bool,
+}
+
+/// Iterate over all values in `document`, including tables and arrays
+///
+/// The values are yielded depth-first, each table or array before its contents. Array elements
+/// are yielded by index, table entries in the iteration order of `toml::map::Map` (see the module
+/// documentation). The document itself is not yielded.
+pub fn paths(document: &Value) -> Paths<'_> {
+    Paths::new(document, false)
+}
+
+/// Iterate over all values in `document` which are neither tables nor arrays
+///
+/// See documentation of `paths()`
+pub fn leaves(document: &Value) -> Paths<'_> {
+    Paths::new(document, true)
+}
+
+impl<'doc> Paths<'doc> {
+    fn new(document: &'doc Value, leaves_only: bool) -> Self {
+        let mut paths = Paths {
+            stack: vec![],
+            leaves_only,
+        };
+        paths.push_children(&Query::default(), document);
+        paths
+    }
+
+    fn push_children(&mut self, path: &Query, value: &'doc Value) {
+        let child = |segment| {
+            let mut path = path.clone();
+            path.push(segment);
+            path
+        };
+
+        match value {
+            Value::Table(tab) => self.stack.extend(
+                tab.iter()
+                    .rev()
+                    .map(|(key, value)| (child(Segment::Key(key.clone())), value)),
+            ),
+            Value::Array(arr) => self.stack.extend(
+                arr.iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(idx, value)| (child(Segment::Index(idx)), value)),
+            ),
+            _ => {}
+        }
+    }
+}
+
+impl<'doc> Iterator for Paths<'doc> {
+    type Item = (Query, &'doc Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, value)) = self.stack.pop() {
+            self.push_children(&path, value);
+
+            let is_leaf = !is_match!(value, Value::Table(_) | Value::Array(_));
+            if is_leaf || !self.leaves_only {
+                return Some((path, value));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use toml::from_str as toml_from_str;
+
+    fn queries(paths: Paths<'_>) -> Vec<String> {
+        paths.map(|(q, _)| q.to_string()).collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let toml: Value = toml_from_str(
+            r#"
+        a = 1
+        empty = []
+        list = [1, [2], { b = "c" }]
+
+        [table]
+        a = true
+
+        [table.sub]
+        y = 1.5
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            queries(paths(&toml)),
+            vec![
+                "a",
+                "empty",
+                "list",
+                "list.[0]",
+                "list.[1]",
+                "list.[1].[0]",
+                "list.[2]",
+                "list.[2].b",
+                "table",
+                "table.a",
+                "table.sub",
+                "table.sub.y",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leaves() {
+        let toml: Value = toml_from_str(
+            r#"
+        a = 1
+        empty = []
+        list = [1, [2], { b = "c" }]
+
+        [table]
+        a = true
+
+        [table.sub]
+        y = 1.5
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            queries(leaves(&toml)),
+            vec![
+                "a",
+                "list.[0]",
+                "list.[1].[0]",
+                "list.[2].b",
+                "table.a",
+                "table.sub.y",
+            ]
+        );
+
+        for (query, value) in leaves(&toml) {
+            assert_eq!(query.get(&toml), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_scalar_document() {
+        assert_eq!(paths(&Value::Integer(1)).count(), 0);
+        assert_eq!(leaves(&Value::Array(vec![])).count(), 0);
+    }
+}
@@ -0,0 +1,10 @@
+use toml_query::Partial;
+
+#[derive(Partial)]
+#[location = "server"]
+#[location = "backend"]
+struct Server {
+    port: u16,
+}
+
+fn main() {}
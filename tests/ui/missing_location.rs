@@ -0,0 +1,8 @@
+use toml_query::Partial;
+
+#[derive(Partial)]
+struct Server {
+    port: u16,
+}
+
+fn main() {}
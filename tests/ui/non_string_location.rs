@@ -0,0 +1,9 @@
+use toml_query::Partial;
+
+#[derive(Partial)]
+#[location = 42]
+struct Server {
+    port: u16,
+}
+
+fn main() {}
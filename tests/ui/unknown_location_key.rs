@@ -0,0 +1,9 @@
+use toml_query::Partial;
+
+#[derive(Partial)]
+#[location("server", fallbacks = "backend")]
+struct Server {
+    port: u16,
+}
+
+fn main() {}
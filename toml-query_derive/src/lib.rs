@@ -5,40 +5,465 @@
 //
 
 #![warn(rust_2018_idioms)]
-#![allow(clippy::collapsible_match)]
 
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{Lit, Meta, MetaNameValue};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{
+    parenthesized, Attribute, Data, DataStruct, DeriveInput, ExprPath, Fields, FieldsNamed, Ident,
+    Lit, LitStr, Meta, MetaNameValue, NestedMeta, Token,
+};
 
-#[proc_macro_derive(Partial, attributes(location))]
+/// A parsed `#[location = "..."]` or `#[location("...", fallback = "...")]` attribute
+struct Location {
+    primary: LitStr,
+    fallbacks: Vec<LitStr>,
+}
+
+impl Parse for Location {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+            let primary = parse_query(input)?;
+            return Ok(Location {
+                primary,
+                fallbacks: vec![],
+            });
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let primary = parse_query(&content)?;
+        let mut fallbacks = vec![];
+
+        while !content.is_empty() {
+            content.parse::<Token![,]>()?;
+            if content.is_empty() {
+                break;
+            }
+            let key: Ident = content.parse()?;
+            if key != "fallback" {
+                return Err(syn::Error::new(key.span(), "expected `fallback`"));
+            }
+            content.parse::<Token![=]>()?;
+            fallbacks.push(parse_query(&content)?);
+        }
+
+        Ok(Location { primary, fallbacks })
+    }
+}
+
+fn parse_query(input: ParseStream<'_>) -> syn::Result<LitStr> {
+    match input.parse::<Lit>()? {
+        Lit::Str(lit) if lit.value().is_empty() => {
+            Err(syn::Error::new(lit.span(), "location must not be empty"))
+        }
+        Lit::Str(lit) => Ok(lit),
+        other => Err(syn::Error::new(other.span(), "expected a string literal")),
+    }
+}
+
+/// Find and parse the `#[location]` attribute in `attrs`, if there is one
+fn find_location(attrs: &[Attribute]) -> syn::Result<Option<Location>> {
+    let mut location = None;
+
+    for attr in attrs.iter().filter(|a| a.path.is_ident("location")) {
+        if location.is_some() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "duplicate #[location] attribute",
+            ));
+        }
+
+        let parsed = syn::parse2::<Location>(attr.tokens.clone()).map_err(|e| {
+            let msg = format!(
+                "expected #[location = \"...\"] or #[location(\"...\", fallback = \"...\")]: {}",
+                e
+            );
+            syn::Error::new(e.span(), msg)
+        })?;
+        location = Some(parsed);
+    }
+
+    Ok(location)
+}
+
+/// What the schema derives need to know about a field
+struct FieldInfo {
+    /// The name of the field as seen by serde
+    name: String,
+    rule: TokenStream2,
+    location: Option<Location>,
+}
+
+/// The doc comment in `attrs`, if there is one
+fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines = attrs
+        .iter()
+        .filter(|a| a.path.is_ident("doc"))
+        .filter_map(|a| match a.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(s), ..
+            })) => Some(s.value().trim().to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let doc = lines.join("\n").trim().to_string();
+    if doc.is_empty() {
+        None
+    } else {
+        Some(doc)
+    }
+}
+
+/// The `#[serde(...)]` options of a container or field which are relevant for the schema
+#[derive(Default)]
+struct SerdeOptions {
+    rename: Option<String>,
+    rename_all: Option<String>,
+    default: Option<Option<ExprPath>>,
+    skip: bool,
+}
+
+fn serde_options(attrs: &[Attribute]) -> syn::Result<SerdeOptions> {
+    let mut options = SerdeOptions::default();
+
+    for attr in attrs.iter().filter(|a| a.path.is_ident("serde")) {
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => continue, // serde reports malformed attributes itself
+        };
+
+        for nested in list.nested.iter() {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+                    if let Lit::Str(ref s) = nv.lit {
+                        options.rename = Some(s.value());
+                    }
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename_all") => {
+                    if let Lit::Str(ref s) = nv.lit {
+                        options.rename_all = Some(s.value());
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(p)) if p.is_ident("default") => {
+                    options.default = Some(None);
+                }
+                NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("default") => {
+                    if let Lit::Str(ref s) = nv.lit {
+                        options.default = Some(Some(s.parse()?));
+                    }
+                }
+                NestedMeta::Meta(Meta::Path(p))
+                    if p.is_ident("skip")
+                        || p.is_ident("skip_deserializing")
+                        || p.is_ident("flatten") =>
+                {
+                    options.skip = true;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(options)
+}
+
+/// The name serde uses for the field or variant `ident` with `#[serde(rename_all = rule)]`
+///
+/// Unknown rules leave the name as it is, serde reports them itself.
+fn rename_all(rule: Option<&str>, ident: &str) -> String {
+    let words = ident
+        .split('_')
+        .flat_map(|part| {
+            let mut words = vec![String::new()];
+            for c in part.chars() {
+                if c.is_uppercase() && !words.last().unwrap().is_empty() {
+                    words.push(String::new());
+                }
+                words.last_mut().unwrap().push(c);
+            }
+            words
+        })
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>();
+
+    let capitalize = |w: &String| {
+        let mut chars = w.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    };
+
+    match rule {
+        Some("lowercase") => ident.to_lowercase(),
+        Some("UPPERCASE") => ident.to_uppercase(),
+        Some("PascalCase") => words.iter().map(capitalize).collect(),
+        Some("camelCase") => words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+            .collect(),
+        Some("snake_case") => words.join("_"),
+        Some("SCREAMING_SNAKE_CASE") => words.join("_").to_uppercase(),
+        Some("kebab-case") => words.join("-"),
+        Some("SCREAMING-KEBAB-CASE") => words.join("-").to_uppercase(),
+        _ => ident.to_string(),
+    }
+}
+
+/// Collect the fields of a struct with named fields, for generating its schema
+///
+/// Fields which are skipped or flattened by serde are not part of the schema.
+fn fields_info(ast: &DeriveInput, fields: &FieldsNamed) -> syn::Result<Vec<FieldInfo>> {
+    let container = serde_options(&ast.attrs)?;
+    let mut infos = vec![];
+
+    for field in fields.named.iter() {
+        let options = serde_options(&field.attrs)?;
+        let location = find_location(&field.attrs)?;
+        if options.skip {
+            continue;
+        }
+
+        let ident = field.ident.as_ref().unwrap(); // safe because named
+        let ty = &field.ty;
+        let name = options
+            .rename
+            .unwrap_or_else(|| rename_all(container.rename_all.as_deref(), &ident.to_string()));
+
+        let default = match (options.default, container.default.is_some()) {
+            (Some(None), _) => Some(quote! { <#ty as ::std::default::Default>::default() }),
+            (Some(Some(path)), _) => Some(quote! { #path() }),
+            (None, true) => Some(quote! { <Self as ::std::default::Default>::default().#ident }),
+            (None, false) => None,
+        };
+
+        let required = match default {
+            Some(ref default) => quote! {
+                let rule = match ::toml_query::schema::serialize_default(&#default) {
+                    Some(value) => rule.with_default(value),
+                    None => rule,
+                };
+            },
+            None => quote! {
+                let rule = if <#ty as ::toml_query::schema::Describe>::OPTIONAL {
+                    rule
+                } else {
+                    rule.required()
+                };
+            },
+        };
+
+        let description = doc_comment(&field.attrs).map(|doc| {
+            quote! { let rule = rule.with_description(#doc); }
+        });
+
+        infos.push(FieldInfo {
+            name,
+            location,
+            rule: quote! {
+                {
+                    let rule = <#ty as ::toml_query::schema::Describe>::describe();
+                    #required
+                    #description
+                    rule
+                }
+            },
+        });
+    }
+
+    Ok(infos)
+}
+
+/// Code which builds the rule for a table with the given fields
+fn table_rule<'a, I>(attrs: &[Attribute], fields: I) -> TokenStream2
+where
+    I: Iterator<Item = &'a FieldInfo>,
+{
+    let keys = fields.map(|f| {
+        let name = &f.name;
+        let rule = &f.rule;
+        quote! {
+            keys.push(
+                ::toml_query::query::Query::from(vec![
+                    ::toml_query::query::Segment::Key(::std::string::String::from(#name))
+                ]),
+                #rule,
+            );
+        }
+    });
+
+    let description = doc_comment(attrs).map(|doc| quote! { .with_description(#doc) });
+
+    quote! {
+        {
+            let mut keys = ::toml_query::schema::Schema::new();
+            #(#keys)*
+            ::toml_query::schema::Rule::of(::toml_query::value::ValueType::Table)
+                .keys(keys)
+                #description
+        }
+    }
+}
+
+#[proc_macro_derive(Describe)]
+pub fn derive_describe(tokens: TokenStream) -> TokenStream {
+    let ast = syn::parse_macro_input!(tokens as DeriveInput);
+    expand_describe(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_describe(ast: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &ast.ident;
+    let rule = match ast.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(ref fields),
+            ..
+        }) => table_rule(&ast.attrs, fields_info(ast, fields)?.iter()),
+
+        Data::Enum(ref data) if data.variants.iter().all(|v| v.fields.is_empty()) => {
+            let container = serde_options(&ast.attrs)?;
+            let mut variants = vec![];
+            for variant in data.variants.iter() {
+                let options = serde_options(&variant.attrs)?;
+                if !options.skip {
+                    variants.push(options.rename.unwrap_or_else(|| {
+                        rename_all(container.rename_all.as_deref(), &variant.ident.to_string())
+                    }));
+                }
+            }
+
+            let description = doc_comment(&ast.attrs).map(|doc| quote! { .with_description(#doc) });
+            quote! {
+                ::toml_query::schema::Rule::of(::toml_query::value::ValueType::String)
+                    .one_of(vec![
+                        #(::std::string::String::from(#variants).into()),*
+                    ])
+                    #description
+            }
+        }
+
+        _ => {
+            let msg = "Describe can only be derived for structs with named fields and enums with unit variants";
+            return Err(syn::Error::new(name.span(), msg));
+        }
+    };
+
+    Ok(quote! {
+        impl ::toml_query::schema::Describe for #name {
+            fn describe() -> ::toml_query::schema::Rule {
+                #rule
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(Partial, attributes(location, schema))]
 pub fn derive_partial(tokens: TokenStream) -> TokenStream {
-    let ast: syn::DeriveInput = syn::parse(tokens).unwrap();
-    let mut location: Option<String> = None;
+    let ast = syn::parse_macro_input!(tokens as DeriveInput);
+    expand_partial(&ast)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_partial(ast: &DeriveInput) -> syn::Result<TokenStream2> {
     let name = &ast.ident;
+    let location = find_location(&ast.attrs)?;
+    let schema_attr = ast.attrs.iter().find(|a| a.path.is_ident("schema"));
+
+    let fields = match ast.data {
+        Data::Struct(DataStruct {
+            fields: Fields::Named(ref fields),
+            ..
+        }) => fields_info(ast, fields)?,
+        _ => vec![],
+    };
 
-    // Iterate over the struct's #[...] attributes
-    for option in ast.attrs.into_iter() {
-        let option = option.parse_meta().unwrap();
-        match option {
-            // Match '#[ident = lit]' attributes. Match guard makes it '#[prefix = lit]'
-            Meta::NameValue(MetaNameValue { ref path, lit, .. }) if path.is_ident("location") => {
-                if let Lit::Str(lit) = lit {
-                    location = Some(lit.value());
+    let field_locations = fields
+        .iter()
+        .filter_map(|f| f.location.as_ref().map(|loc| (&f.name, loc)))
+        .map(|(field, loc)| {
+            let primary = &loc.primary;
+            let fallbacks = &loc.fallbacks;
+            quote! {
+                ::toml_query::read::FieldLocation {
+                    field: #field,
+                    locations: &[#primary, #(#fallbacks),*],
                 }
             }
-            _ => {} // ...
+        })
+        .collect::<Vec<_>>();
+
+    let (primary, fallbacks) = match location {
+        Some(loc) => (loc.primary, loc.fallbacks),
+        None if !field_locations.is_empty() => (LitStr::new("", name.span()), vec![]),
+        None => {
+            let msg = "missing #[location = \"...\"] attribute on the type or its fields";
+            return Err(syn::Error::new(name.span(), msg));
         }
-    }
+    };
 
-    let location = location.unwrap();
+    let schema = match schema_attr {
+        None => None,
+        Some(attr) if !attr.tokens.is_empty() => {
+            return Err(syn::Error::new(attr.span(), "expected #[schema]"));
+        }
+        Some(attr) if !matches!(ast.data, Data::Struct(_)) || fields.is_empty() => {
+            let msg = "#[schema] needs a struct with named fields";
+            return Err(syn::Error::new(attr.span(), msg));
+        }
+        Some(_) => Some(expand_schema(ast, &primary, &fields)),
+    };
 
-    let gen = quote! {
+    Ok(quote! {
         impl<'a> ::toml_query::read::Partial<'a> for #name {
-            const LOCATION : &'static str = #location;
+            const LOCATION : &'static str = #primary;
+            const FALLBACKS : &'static [&'static str] = &[#(#fallbacks),*];
+            const FIELD_LOCATIONS : &'static [::toml_query::read::FieldLocation] = &[#(#field_locations),*];
             type  Output                  = Self;
+
+            #schema
         }
+    })
+}
+
+/// Generate `Partial::schema()`
+///
+/// The fields without a location of their own are described by a rule for the table at
+/// `location`, the others by rules at their primary locations.
+fn expand_schema(ast: &DeriveInput, location: &LitStr, fields: &[FieldInfo]) -> TokenStream2 {
+    let section = if location.value().is_empty() {
+        None
+    } else {
+        let rule = table_rule(&ast.attrs, fields.iter().filter(|f| f.location.is_none()));
+        Some(quote! {
+            schema.push(::toml_query::query::Query::parse(#location)?, #rule);
+        })
     };
 
-    gen.into()
+    let located = fields.iter().filter_map(|f| {
+        f.location.as_ref().map(|loc| {
+            let primary = &loc.primary;
+            let rule = &f.rule;
+            quote! {
+                schema.push(::toml_query::query::Query::parse(#primary)?, #rule);
+            }
+        })
+    });
+
+    quote! {
+        fn schema() -> ::toml_query::error::Result<::toml_query::schema::Schema> {
+            let mut schema = ::toml_query::schema::Schema::new();
+            #section
+            #(#located)*
+            Ok(schema)
+        }
+    }
 }